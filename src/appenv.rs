@@ -1,5 +1,6 @@
 use shellexpand;
 use std::env;
+use std::path::Path;
 
 pub static KEY_CONFIG_PATH: &str = "RAMUP_CONFIG_PATH";
 pub static KEY_STATE_PATH: &str = "RAMUP_STATE_PATH";
@@ -14,6 +15,31 @@ pub fn state() -> String {
     env::var(KEY_STATE_PATH).unwrap_or_else(|_| String::from(default))
 }
 
+/// Copy of the last good state file, taken before each write
+pub fn state_backup() -> String {
+    format!("{}.bak", state())
+}
+
+/// Lock file held while a ramup process works on the state file
+pub fn lock() -> String {
+    let state = state();
+    String::from(
+        Path::new(&state)
+            .with_file_name("ramup.lock")
+            .to_string_lossy(),
+    )
+}
+
+/// Journal of in-flight relocations, kept next to the state file
+pub fn journal() -> String {
+    let state = state();
+    String::from(
+        Path::new(&state)
+            .with_file_name("journal.toml")
+            .to_string_lossy(),
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -29,6 +55,9 @@ mod tests {
 
         assert_eq!("/home/.config/ramup/config.toml", config());
         assert_eq!("/home/.config/ramup/state.toml", state());
+        assert_eq!("/home/.config/ramup/journal.toml", journal());
+        assert_eq!("/home/.config/ramup/state.toml.bak", state_backup());
+        assert_eq!("/home/.config/ramup/ramup.lock", lock());
 
         env::set_var("HOME", t_home);
     }
@@ -19,4 +19,90 @@ pub enum FileSystemError {
 
     #[error("File is not symbolic link: {0}")]
     NotSymbolicLink(String),
+
+    #[error("Not enough space for {path}: needs {required} bytes, {available} bytes available")]
+    NotEnoughSpace {
+        path: String,
+        required: u64,
+        available: u64,
+    },
+
+    #[error("Cannot tell which RAMDisk holds {0}")]
+    UnknownDisk(String),
+
+    #[error("Copy of {path} differs from the original: {}", .files.join(", "))]
+    VerificationFailed { path: String, files: Vec<String> },
+}
+
+#[derive(Error, Debug)]
+pub enum StateError {
+    #[error("Failed to read state file {path}: {message}")]
+    Unreadable { path: String, message: String },
+
+    #[error("State file {path} is corrupt: {message}")]
+    Corrupt { path: String, message: String },
+
+    #[error("State file version {version} is newer than this ramup supports ({supported}), upgrade ramup")]
+    TooNew { version: u32, supported: u32 },
+}
+
+#[derive(Error, Debug)]
+pub enum LockError {
+    #[error("Another ramup is running (lock file: {path}), gave up after waiting {waited}s")]
+    Timeout { path: String, waited: u64 },
+}
+
+#[derive(Error, Debug)]
+#[error("Interrupted: finished paths are kept and the current one is rolled back")]
+pub struct Interrupted;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum SizeError {
+    #[error("Invalid size: {0}")]
+    Invalid(String),
+
+    #[error("Percentage size requires a RAM backed backend: {0}%")]
+    PercentWithoutMemory(f64),
+
+    #[error("RAMDisk size ({0} bytes) is larger than physical memory ({1} bytes)")]
+    LargerThanMemory(u64, u64),
+}
+
+/// Variant name of the error behind `err`, or "Other" when it isn't one of ours.
+pub fn kind(err: &anyhow::Error) -> &'static str {
+    if let Some(err) = err.downcast_ref::<FileSystemError>() {
+        return match err {
+            FileSystemError::NotExist(_) => "NotExist",
+            FileSystemError::FailedToGetMetaData(_) => "FailedToGetMetaData",
+            FileSystemError::FileIsAlreadySymbolicLink(_) => "FileIsAlreadySymbolicLink",
+            FileSystemError::NotSymbolicLink(_) => "NotSymbolicLink",
+            FileSystemError::NotEnoughSpace { .. } => "NotEnoughSpace",
+            FileSystemError::UnknownDisk(_) => "UnknownDisk",
+            FileSystemError::VerificationFailed { .. } => "VerificationFailed",
+        };
+    }
+    if let Some(err) = err.downcast_ref::<SizeError>() {
+        return match err {
+            SizeError::Invalid(_) => "Invalid",
+            SizeError::PercentWithoutMemory(_) => "PercentWithoutMemory",
+            SizeError::LargerThanMemory(..) => "LargerThanMemory",
+        };
+    }
+    if err.downcast_ref::<FileProgressError>().is_some() {
+        return "FileProgressError";
+    }
+    if let Some(err) = err.downcast_ref::<StateError>() {
+        return match err {
+            StateError::Unreadable { .. } => "StateUnreadable",
+            StateError::Corrupt { .. } => "StateCorrupt",
+            StateError::TooNew { .. } => "StateTooNew",
+        };
+    }
+    if let Some(LockError::Timeout { .. }) = err.downcast_ref::<LockError>() {
+        return "LockTimeout";
+    }
+    if err.downcast_ref::<Interrupted>().is_some() {
+        return "Interrupted";
+    }
+    "Other"
 }
@@ -1,40 +1,146 @@
+use crate::apperror::FileSystemError;
+use crate::copy::Copier;
+use crate::report;
+use crate::verify::Verify;
 use anyhow::{Context, Result};
 use console::Emoji;
-use fs_extra::dir::CopyOptions;
 use indicatif::{ProgressBar, ProgressStyle};
+use std::io::Write;
 use std::path::Path;
 
 static SPARKLE: Emoji<'_, '_> = Emoji("✨ ", ":-)");
 
-pub fn relocate<S: AsRef<Path>, P: AsRef<Path>>(from: &S, to: &P) -> Result<()> {
-    let mut option = CopyOptions::new();
-    option.overwrite = true;
+/// Move `from` to `to`, copying first and removing `from` once everything is copied
+/// and the copy passes `verify`.
+/// Fails with `Interrupted` on Ctrl-C, leaving `from` intact and the copy at `to`
+/// to be rolled back by the caller, or with `VerificationFailed` after removing the copy.
+pub fn relocate<S: AsRef<Path>, P: AsRef<Path>>(from: &S, to: &P, verify: Verify) -> Result<()> {
+    relocate_with(from, to, verify, || {})
+}
 
+/// `relocate`, calling `copied` between the copy and its verification.
+fn relocate_with<S: AsRef<Path>, P: AsRef<Path>>(
+    from: &S,
+    to: &P,
+    verify: Verify,
+    copied: impl FnOnce(),
+) -> Result<()> {
     let from_str = from.as_ref().to_str().expect("failed to convert str");
 
     let size = fs_extra::dir::get_size(from)?;
-    let pb = ProgressBar::new(size);
+    let pb = if report::is_json() {
+        ProgressBar::hidden()
+    } else {
+        ProgressBar::new(size)
+    };
     pb.set_style(ProgressStyle::default_bar()
         .template("{wide_msg:.bold.dim} {spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta})")
         .progress_chars("#>-"));
     pb.set_message(&from.as_ref().to_str().expect("failed to convert str"));
 
-    let handler = |process_info: fs_extra::TransitProcess| {
-        pb.set_position(process_info.copied_bytes);
-        fs_extra::dir::TransitProcessResult::ContinueOrAbort
+    let to_dir = to.as_ref().parent().with_context(|| "No parent path")?;
+    std::fs::create_dir_all(to_dir)?;
+    let result = Copier::new(&pb).copy(from.as_ref(), to.as_ref());
+    pb.finish_and_clear();
+    result?;
+    copied();
+    let files = verify.compare(from.as_ref(), to.as_ref())?;
+    if !files.is_empty() {
+        fs_extra::remove_items(&[to])?;
+        return Err(anyhow::anyhow!(FileSystemError::VerificationFailed {
+            path: from_str.to_string(),
+            files,
+        }));
+    }
+    fs_extra::remove_items(&[from])?;
+
+    if !report::is_json() {
+        println!("{} {} is moved.", SPARKLE, from_str);
+    }
+    Ok(())
+}
+
+/// Move `from` into `into`, keeping entries which already exist in `into`, and remove `from`.
+/// Used to undo an interrupted move: `into` is the source, whose files are intact,
+/// and `from` is the partial copy or the part already deleted from the source.
+pub fn merge<S: AsRef<Path>, P: AsRef<Path>>(from: &S, into: &P) -> Result<()> {
+    let (from, into) = (from.as_ref(), into.as_ref());
+    let into_meta = match std::fs::symlink_metadata(into) {
+        Ok(meta) => meta,
+        Err(_) => {
+            let parent = into.parent().with_context(|| "No parent path")?;
+            std::fs::create_dir_all(parent)?;
+            std::fs::rename(from, into).or_else(|_| -> Result<()> {
+                Copier::new(&ProgressBar::hidden()).copy(from, into)?;
+                fs_extra::remove_items(&[from])?;
+                Ok(())
+            })?;
+            return Ok(());
+        }
     };
 
-    let to_dir = to.as_ref().parent().with_context(|| "No parent path")?;
-    fs_extra::move_items_with_progress(&vec![from], to_dir, &option, handler)?;
+    let from_meta = std::fs::symlink_metadata(from)?;
+    if from_meta.is_dir() && into_meta.is_dir() {
+        for entry in std::fs::read_dir(from)? {
+            let entry = entry?;
+            merge(&entry.path(), &into.join(entry.file_name()))?;
+        }
+        std::fs::remove_dir(from)?;
+    } else if from_meta.is_dir() {
+        std::fs::remove_dir_all(from)?;
+    } else {
+        std::fs::remove_file(from)?;
+    }
+    Ok(())
+}
 
-    pb.finish_and_clear();
-    println!("{} {} is moved.", SPARKLE, from_str);
+/// Replace `path` with `contents` through a temporary file and rename,
+/// so readers never see a partially written file.
+/// Both the contents and the rename are flushed to disk before returning,
+/// so that a power loss leaves either the old or the new file.
+pub fn write_atomic<P: AsRef<Path>>(path: P, contents: &str) -> Result<()> {
+    let path = path.as_ref();
+    let parent = path.parent().with_context(|| "No parent path")?;
+    std::fs::create_dir_all(parent)?;
+    let file_name = path.file_name().with_context(|| "No file name")?;
+    let tmp = parent.join(format!(".{}.tmp", file_name.to_string_lossy()));
+    let mut file = std::fs::File::create(&tmp)?;
+    file.write_all(contents.as_bytes())?;
+    file.sync_all()?;
+    std::fs::rename(&tmp, path)?;
+    std::fs::File::open(parent)?.sync_all()?;
+    Ok(())
+}
+
+/// Fails with `NotEnoughSpace` when `from` doesn't fit on the volume that will hold `to`.
+pub fn ensure_space<S: AsRef<Path>, P: AsRef<Path>>(from: &S, to: &P) -> Result<()> {
+    let required = fs_extra::dir::get_size(from)?;
+    let available = available_space(to)?;
+    if required > available {
+        return Err(anyhow::anyhow!(FileSystemError::NotEnoughSpace {
+            path: from.as_ref().to_string_lossy().to_string(),
+            required,
+            available,
+        }));
+    }
     Ok(())
 }
 
+/// Free space of the volume holding `path`, which doesn't need to exist yet.
+pub fn available_space<P: AsRef<Path>>(path: &P) -> Result<u64> {
+    let existing = path
+        .as_ref()
+        .ancestors()
+        .find(|p| p.exists())
+        .with_context(|| "No existing ancestor path")?;
+    Ok(fs2::available_space(existing)?)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::apperror::Interrupted;
+    use crate::interrupt;
     use serial_test::serial;
     use tempdir::TempDir;
 
@@ -50,7 +156,7 @@ mod tests {
         let to_file = to_dir.path().join("mo ved.txt");
 
         assert_eq!(false, to_file.exists());
-        relocate(&from_file, &to_file).unwrap();
+        relocate(&from_file, &to_file, Verify::Off).unwrap();
         assert_eq!(true, to_file.exists());
     }
 
@@ -65,7 +171,152 @@ mod tests {
         let to = to_dir.path().join("from");
 
         assert_eq!(false, to.exists());
-        relocate(&from, &to).unwrap();
+        relocate(&from, &to, Verify::Off).unwrap();
         assert_eq!(true, to.exists());
     }
+
+    /// Directory holding `file.txt` and where it's relocated to, under `dir`
+    fn verify_fixture(dir: &TempDir) -> (std::path::PathBuf, std::path::PathBuf) {
+        let from = dir.path().join("from");
+        std::fs::create_dir(&from).unwrap();
+        std::fs::write(from.join("file.txt"), "ramup").unwrap();
+        (from, dir.path().join("to"))
+    }
+
+    /// Assert that `from` is kept and the copy at `to` is removed after a mismatch
+    fn assert_rolled_back(result: Result<()>, from: &Path, to: &Path, contents: &str) {
+        let err = result.unwrap_err();
+        match err.downcast_ref::<FileSystemError>() {
+            Some(FileSystemError::VerificationFailed { files, .. }) => {
+                assert_eq!(files, &vec![from.join("file.txt").to_string_lossy()])
+            }
+            _ => panic!("unexpected error: {:?}", err),
+        }
+        assert_eq!(
+            std::fs::read_to_string(from.join("file.txt")).unwrap(),
+            contents
+        );
+        assert!(!to.exists());
+    }
+
+    #[test]
+    #[serial]
+    fn relocate_verify_off() {
+        let dir = TempDir::new("ramup").unwrap();
+        let (from, to) = verify_fixture(&dir);
+
+        // Changes after the copy go unnoticed
+        relocate_with(&from, &to, Verify::Off, || {
+            std::fs::write(from.join("file.txt"), "ramup!").unwrap()
+        })
+        .unwrap();
+        assert!(!from.exists());
+        assert_eq!(
+            std::fs::read_to_string(to.join("file.txt")).unwrap(),
+            "ramup"
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn relocate_verify_quick() {
+        let dir = TempDir::new("ramup").unwrap();
+        let (from, to) = verify_fixture(&dir);
+        relocate(&from, &to, Verify::Quick).unwrap();
+        assert!(!from.exists());
+        assert!(to.join("file.txt").exists());
+
+        let dir = TempDir::new("ramup").unwrap();
+        let (from, to) = verify_fixture(&dir);
+        let result = relocate_with(&from, &to, Verify::Quick, || {
+            std::fs::write(from.join("file.txt"), "ramup!").unwrap()
+        });
+        assert_rolled_back(result, &from, &to, "ramup!");
+    }
+
+    #[test]
+    #[serial]
+    fn relocate_verify_hash() {
+        let dir = TempDir::new("ramup").unwrap();
+        let (from, to) = verify_fixture(&dir);
+        relocate(&from, &to, Verify::Hash).unwrap();
+        assert!(!from.exists());
+        assert!(to.join("file.txt").exists());
+
+        // Same size and modification time, which only the hash tells apart
+        let dir = TempDir::new("ramup").unwrap();
+        let (from, to) = verify_fixture(&dir);
+        let file = from.join("file.txt");
+        let mtime =
+            filetime::FileTime::from_last_modification_time(&std::fs::metadata(&file).unwrap());
+        let result = relocate_with(&from, &to, Verify::Hash, || {
+            std::fs::write(&file, "RAMUP").unwrap();
+            filetime::set_file_mtime(&file, mtime).unwrap();
+        });
+        assert_rolled_back(result, &from, &to, "RAMUP");
+    }
+
+    #[test]
+    #[serial]
+    fn relocate_interrupted() {
+        let from_dir = TempDir::new("ramup").unwrap();
+        let from = from_dir.path().join("from");
+        std::fs::create_dir(&from).unwrap();
+        std::fs::write(from.join("file.txt"), "ramup").unwrap();
+
+        let to_dir = TempDir::new("ramup").unwrap();
+        let to = to_dir.path().join("from");
+
+        interrupt::set_interrupted(true);
+        let err = relocate(&from, &to, Verify::Off).unwrap_err();
+        interrupt::set_interrupted(false);
+        assert!(err.downcast_ref::<Interrupted>().is_some());
+        assert!(from.join("file.txt").exists());
+    }
+
+    #[test]
+    fn write_atomic() {
+        let dir = TempDir::new("ramup").unwrap();
+        let path = dir.path().join("sub/state.toml");
+        super::write_atomic(&path, "old").unwrap();
+        super::write_atomic(&path, "new").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new");
+        assert!(!dir.path().join("sub/.state.toml.tmp").exists());
+    }
+
+    #[test]
+    fn merge() {
+        let dir = TempDir::new("ramup").unwrap();
+        let from = dir.path().join("from");
+        let into = dir.path().join("into");
+        std::fs::create_dir_all(from.join("dir")).unwrap();
+        std::fs::create_dir_all(&into).unwrap();
+        std::fs::write(from.join("partial.txt"), "par").unwrap();
+        std::fs::write(into.join("partial.txt"), "partial").unwrap();
+        std::fs::write(from.join("dir/deleted.txt"), "deleted").unwrap();
+
+        super::merge(&from, &into).unwrap();
+        assert!(!from.exists());
+        assert_eq!(
+            std::fs::read_to_string(into.join("partial.txt")).unwrap(),
+            "partial"
+        );
+        assert_eq!(
+            std::fs::read_to_string(into.join("dir/deleted.txt")).unwrap(),
+            "deleted"
+        );
+    }
+
+    #[test]
+    fn ensure_space() {
+        let from_dir = TempDir::new("ramup").unwrap();
+        let from = from_dir.path().join("from");
+        std::fs::create_dir(&from).unwrap();
+        std::fs::write(from.join("file.txt"), "ramup").unwrap();
+
+        let to_dir = TempDir::new("ramup").unwrap();
+        let to = to_dir.path().join("not/created/yet");
+        assert!(available_space(&to).unwrap() > 0);
+        super::ensure_space(&from, &to).unwrap();
+    }
 }
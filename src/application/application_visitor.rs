@@ -21,6 +21,8 @@ impl<'de> Visitor<'de> for ApplicationVisitor {
             name: "".to_string(),
             restart: None,
             paths: vec![],
+            disk: None,
+            sync_interval: None,
         };
 
         while let Some(key) = map.next_key()? {
@@ -34,6 +36,12 @@ impl<'de> Visitor<'de> for ApplicationVisitor {
                 "paths" => {
                     app_config.paths = map.next_value().unwrap();
                 }
+                "disk" => {
+                    app_config.disk = map.next_value()?;
+                }
+                "sync_interval" => {
+                    app_config.sync_interval = map.next_value()?;
+                }
                 _ => {}
             }
         }
@@ -9,7 +9,6 @@ pub struct AbsPath {
 }
 
 impl AbsPath {
-    #[allow(dead_code)]
     pub fn new<P: AsRef<Path>>(p: P) -> Result<Self> {
         let p = AbsPath::expand(p).expect("|| Failed to expand Path");
 
@@ -18,7 +17,6 @@ impl AbsPath {
         })
     }
 
-    #[allow(dead_code)]
     pub fn parent(&self) -> Result<Self> {
         let path = Path::new(&self.path)
             .parent()
@@ -29,7 +27,6 @@ impl AbsPath {
         })
     }
 
-    #[allow(dead_code)]
     pub fn join<P: AsRef<Path>>(&self, path: P) -> Result<Self> {
         let path = path.as_ref();
 
@@ -0,0 +1,70 @@
+use crate::backend::{Backend, Usage};
+use crate::ram::RAM;
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+
+/// Uses `<mount_path>/<name>` on an already mounted fast directory such as `/dev/shm`.
+pub struct Directory {}
+
+impl Backend for Directory {
+    fn physical_memory(&self) -> Result<Option<u64>> {
+        Ok(None)
+    }
+
+    fn create(&self, ram: &RAM, _bytes: u64) -> Result<()> {
+        if !Path::new(&ram.mount_path).is_dir() {
+            anyhow::bail!("mount_path is not a directory: {}", ram.mount_path);
+        }
+        let path = ram.volume_path();
+        fs::create_dir(&path)
+            .with_context(|| format!("Failed to create {}", path.to_string_lossy()))
+    }
+
+    fn exists(&self, ram: &RAM) -> Result<bool> {
+        Ok(ram.volume_path().is_dir())
+    }
+
+    fn usage(&self, ram: &RAM) -> Result<Usage> {
+        Usage::of(ram.volume_path())
+    }
+
+    fn destroy(&self, ram: &RAM) -> Result<()> {
+        let path = ram.volume_path();
+        fs::remove_dir_all(&path)
+            .with_context(|| format!("Failed to remove {}", path.to_string_lossy()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn create_and_destroy() {
+        let dir = TempDir::new("ramup").unwrap();
+        let toml = format!(
+            r#"
+                name = "RAMDisk"
+                mount_path = "{}"
+            "#,
+            dir.path().to_str().unwrap()
+        );
+        let ram = RAM::new_from_str(&toml).unwrap();
+        let backend = Directory {};
+
+        assert!(!backend.exists(&ram).unwrap());
+        backend.create(&ram, 0).unwrap();
+        assert!(backend.exists(&ram).unwrap());
+        assert!(backend.usage(&ram).unwrap().total > 0);
+        backend.destroy(&ram).unwrap();
+        assert!(!backend.exists(&ram).unwrap());
+    }
+
+    #[test]
+    fn create_without_mount_path() {
+        let ram = RAM::new_from_str(r#"mount_path = "/this/does/not/exist""#).unwrap();
+        assert!(Directory {}.create(&ram, 0).is_err());
+    }
+}
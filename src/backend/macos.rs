@@ -0,0 +1,252 @@
+use crate::backend::{Backend, Usage};
+use crate::maccmd::{DiskUtil, HdiUtil, MdUtil, Sysctl, TmUtil};
+use crate::ram::RAM;
+use crate::runner::Runner;
+use crate::size::Size;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+static DEFAULT_MOUNT_PATH: &str = "/Volumes";
+
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+pub enum Filesystem {
+    #[default]
+    #[serde(rename = "hfs+")]
+    Hfs,
+    #[serde(rename = "case-sensitive-hfs+")]
+    CaseSensitiveHfs,
+    #[serde(rename = "apfs")]
+    Apfs,
+    #[serde(rename = "case-sensitive-apfs")]
+    CaseSensitiveApfs,
+}
+
+impl Filesystem {
+    /// File system personality passed to `diskutil erasevolume`.
+    pub fn personality(self) -> &'static str {
+        match self {
+            Filesystem::Hfs => "HFS+",
+            Filesystem::CaseSensitiveHfs => "HFSX",
+            Filesystem::Apfs => "APFS",
+            Filesystem::CaseSensitiveApfs => "APFSX",
+        }
+    }
+
+    /// APFS volumes live on a container disk synthesized from the attached one.
+    pub fn is_apfs(self) -> bool {
+        matches!(self, Filesystem::Apfs | Filesystem::CaseSensitiveApfs)
+    }
+}
+
+pub struct MacOS {
+    runner: Box<dyn Runner>,
+}
+
+impl MacOS {
+    pub fn new(runner: Box<dyn Runner>) -> Self {
+        MacOS { runner }
+    }
+
+    fn mount_options(ram: &RAM) -> Vec<&'static str> {
+        let mut options = vec![];
+        if ram.nobrowse {
+            options.push("nobrowse");
+        }
+        if ram.noatime {
+            options.push("noatime");
+        }
+        options
+    }
+
+    /// Device of the volume mounted at `mount_point`, which for APFS isn't the attached device.
+    fn volume_device(&self, ram: &RAM, device: &str, mount_point: &Path) -> Result<String> {
+        if !ram.filesystem.is_apfs() {
+            return Ok(device.to_string());
+        }
+        let info = DiskUtil::new(&*self.runner).info(&mount_point.to_string_lossy())?;
+        let info = info.with_context(|| {
+            format!(
+                "APFS volume is not found: {}",
+                mount_point.to_string_lossy()
+            )
+        })?;
+        Ok(info.device_node)
+    }
+}
+
+impl Backend for MacOS {
+    fn physical_memory(&self) -> Result<Option<u64>> {
+        Sysctl::new(&*self.runner).memsize().map(Some)
+    }
+
+    fn create(&self, ram: &RAM, bytes: u64) -> Result<()> {
+        let device = HdiUtil::new(&*self.runner).attach(Size::sectors(bytes))?;
+        let diskutil = DiskUtil::new(&*self.runner);
+        diskutil.erasevolume(ram.filesystem.personality(), &ram.name, &device)?;
+
+        // diskutil always mounts an erased volume under /Volumes without options
+        let mount_point = ram.volume_path();
+        let options = MacOS::mount_options(ram);
+        let default_mount_path = Path::new(&ram.mount_path) == Path::new(DEFAULT_MOUNT_PATH);
+        if !default_mount_path || !options.is_empty() {
+            let erased = Path::new(DEFAULT_MOUNT_PATH).join(&ram.name);
+            let volume = self.volume_device(ram, &device, &erased)?;
+            diskutil.unmount(&volume)?;
+            if default_mount_path {
+                diskutil.mount(&volume, None, &options)?;
+            } else {
+                fs::create_dir_all(&mount_point).with_context(|| {
+                    format!("Failed to create {}", mount_point.to_string_lossy())
+                })?;
+                diskutil.mount(&volume, Some(&mount_point.to_string_lossy()), &options)?;
+            }
+        }
+
+        let mount_point = mount_point.to_string_lossy();
+        if !ram.spotlight {
+            MdUtil::new(&*self.runner).disable_indexing(&mount_point)?;
+        }
+        if !ram.time_machine {
+            TmUtil::new(&*self.runner).add_exclusion(&mount_point)?;
+        }
+        Ok(())
+    }
+
+    fn exists(&self, ram: &RAM) -> Result<bool> {
+        let volume_path = ram.volume_path();
+        if ram.filesystem.is_apfs() {
+            // hdiutil only lists the attached device, not the synthesized container
+            let info = DiskUtil::new(&*self.runner).info(&volume_path.to_string_lossy())?;
+            return Ok(info.is_some_and(|info| Path::new(&info.mount_point) == volume_path));
+        }
+        HdiUtil::new(&*self.runner).exist_volume(&volume_path.to_string_lossy())
+    }
+
+    fn usage(&self, ram: &RAM) -> Result<Usage> {
+        Usage::of(ram.volume_path())
+    }
+
+    fn destroy(&self, ram: &RAM) -> Result<()> {
+        let volume_path = ram.volume_path().to_string_lossy().to_string();
+        let hdiutil = HdiUtil::new(&*self.runner);
+        if ram.filesystem.is_apfs() {
+            // Detaching the attached device ejects the container synthesized from it
+            if let Some(info) = DiskUtil::new(&*self.runner).info(&volume_path)? {
+                for store in &info.apfs_physical_stores {
+                    hdiutil.detach(&format!("/dev/{}", store.device_identifier))?;
+                }
+                if !info.apfs_physical_stores.is_empty() {
+                    return Ok(());
+                }
+            }
+        }
+        hdiutil.detach_volume(&volume_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::ScriptedRunner;
+    use std::rc::Rc;
+
+    const INFO_APFS: &str = include_str!("../maccmd/fixtures/diskutil_info_apfs.plist");
+    const INFO_WITH_VOLUME: &str =
+        include_str!("../maccmd/fixtures/hdiutil_info_with_volume.plist");
+
+    #[test]
+    fn filesystem() {
+        let ram = RAM::new_from_str("").unwrap();
+        assert_eq!(ram.filesystem.personality(), "HFS+");
+        let ram = RAM::new_from_str(r#"filesystem = "case-sensitive-apfs""#).unwrap();
+        assert_eq!(ram.filesystem.personality(), "APFSX");
+        assert!(RAM::new_from_str(r#"filesystem = "ntfs""#).is_err());
+    }
+
+    #[test]
+    fn replay_create_with_options() {
+        let ram = RAM::new_from_str(
+            r#"
+                name = "RAMDiskByRamup"
+                filesystem = "apfs"
+                nobrowse = true
+                noatime = true
+                spotlight = false
+                time_machine = false
+            "#,
+        )
+        .unwrap();
+        let runner = Rc::new(
+            ScriptedRunner::new()
+                .ok("hdiutil attach -nomount ram://2048", "/dev/disk2\n")
+                .ok("diskutil erasevolume APFS RAMDiskByRamup /dev/disk2", "")
+                .ok("diskutil info -plist /Volumes/RAMDiskByRamup", INFO_APFS)
+                .ok("diskutil unmount /dev/disk3s1", "")
+                .ok(
+                    "diskutil mount -mountOptions nobrowse,noatime /dev/disk3s1",
+                    "",
+                )
+                .ok("mdutil -i off /Volumes/RAMDiskByRamup", "")
+                .ok("tmutil addexclusion /Volumes/RAMDiskByRamup", ""),
+        );
+        let backend = MacOS::new(Box::new(Rc::clone(&runner)));
+
+        backend.create(&ram, 1 << 20).unwrap();
+        assert!(runner.is_finished());
+    }
+
+    #[test]
+    fn replay_create_with_defaults() {
+        let ram = RAM::new_from_str(r#"name = "RAMDiskByRamup""#).unwrap();
+        let runner = Rc::new(
+            ScriptedRunner::new()
+                .ok("hdiutil attach -nomount ram://2048", "/dev/disk2\n")
+                .ok("diskutil erasevolume HFS+ RAMDiskByRamup /dev/disk2", ""),
+        );
+        let backend = MacOS::new(Box::new(Rc::clone(&runner)));
+
+        backend.create(&ram, 1 << 20).unwrap();
+        assert!(runner.is_finished());
+    }
+
+    #[test]
+    fn replay_exists_and_destroy_apfs() {
+        let ram = RAM::new_from_str(
+            r#"
+                name = "RAMDiskByRamup"
+                filesystem = "apfs"
+            "#,
+        )
+        .unwrap();
+        let runner = Rc::new(
+            ScriptedRunner::new()
+                .ok("diskutil info -plist /Volumes/RAMDiskByRamup", INFO_APFS)
+                .ok("diskutil info -plist /Volumes/RAMDiskByRamup", INFO_APFS)
+                .ok("hdiutil detach -force /dev/disk2", "")
+                .fail("diskutil info -plist /Volumes/RAMDiskByRamup", ""),
+        );
+        let backend = MacOS::new(Box::new(Rc::clone(&runner)));
+
+        assert!(backend.exists(&ram).unwrap());
+        backend.destroy(&ram).unwrap();
+        assert!(!backend.exists(&ram).unwrap());
+        assert!(runner.is_finished());
+    }
+
+    #[test]
+    fn replay_exists_and_destroy_hfs() {
+        let ram = RAM::new_from_str(r#"name = "RAMDiskByRamup""#).unwrap();
+        let runner = Rc::new(
+            ScriptedRunner::new()
+                .ok("hdiutil info -plist", INFO_WITH_VOLUME)
+                .ok("hdiutil detach -force /Volumes/RAMDiskByRamup", ""),
+        );
+        let backend = MacOS::new(Box::new(Rc::clone(&runner)));
+
+        assert!(backend.exists(&ram).unwrap());
+        backend.destroy(&ram).unwrap();
+        assert!(runner.is_finished());
+    }
+}
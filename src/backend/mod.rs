@@ -0,0 +1,109 @@
+pub use directory::Directory;
+pub use macos::{Filesystem, MacOS};
+pub use tmpfs::Tmpfs;
+pub use zram::Zram;
+
+mod directory;
+mod macos;
+mod tmpfs;
+mod zram;
+
+use crate::ram::RAM;
+use crate::runner::SystemRunner;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    MacOS,
+    Tmpfs,
+    Directory,
+    Zram,
+}
+
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub struct Usage {
+    pub total: u64,
+    pub used: u64,
+    pub free: u64,
+    pub compressed: Option<u64>,
+    pub uncompressed: Option<u64>,
+}
+
+impl Usage {
+    pub fn of<P: AsRef<std::path::Path>>(path: P) -> Result<Self> {
+        let total = fs2::total_space(&path)?;
+        let free = fs2::available_space(&path)?;
+        Ok(Usage {
+            total,
+            used: total.saturating_sub(free),
+            free,
+            ..Usage::default()
+        })
+    }
+}
+
+pub trait Backend {
+    /// Total memory that bounds the RAMDisk size, or `None` when it isn't backed by RAM.
+    fn physical_memory(&self) -> Result<Option<u64>>;
+    fn create(&self, ram: &RAM, bytes: u64) -> Result<()>;
+    fn exists(&self, ram: &RAM) -> Result<bool>;
+    fn usage(&self, ram: &RAM) -> Result<Usage>;
+    fn destroy(&self, ram: &RAM) -> Result<()>;
+}
+
+pub fn from_ram(ram: &RAM) -> Result<Box<dyn Backend>> {
+    let kind = match ram.backend {
+        Some(kind) => kind,
+        None => platform()?,
+    };
+    match kind {
+        Kind::MacOS => Ok(Box::new(MacOS::new(Box::new(SystemRunner {})))),
+        Kind::Tmpfs => Ok(Box::new(Tmpfs::new(Box::new(SystemRunner {})))),
+        Kind::Directory => Ok(Box::new(Directory {})),
+        Kind::Zram => Ok(Box::new(Zram::new(Box::new(SystemRunner {})))),
+    }
+}
+
+fn platform() -> Result<Kind> {
+    if cfg!(target_os = "macos") {
+        return Ok(Kind::MacOS);
+    }
+    if cfg!(target_os = "linux") {
+        return Ok(Kind::Tmpfs);
+    }
+    anyhow::bail!(
+        "no RAMDisk backend for this platform, set `backend` in [ram]: {}",
+        std::env::consts::OS
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kind_from_config() {
+        let ram = RAM::new_from_str(r#"backend = "macos""#).unwrap();
+        assert_eq!(ram.backend, Some(Kind::MacOS));
+        assert!(from_ram(&ram).is_ok());
+
+        let ram = RAM::new_from_str(r#"backend = "tmpfs""#).unwrap();
+        assert_eq!(ram.backend, Some(Kind::Tmpfs));
+
+        let ram = RAM::new_from_str(r#"backend = "directory""#).unwrap();
+        assert_eq!(ram.backend, Some(Kind::Directory));
+
+        let ram = RAM::new_from_str(r#"backend = "zram""#).unwrap();
+        assert_eq!(ram.backend, Some(Kind::Zram));
+    }
+
+    #[test]
+    fn kind_from_platform() {
+        let ram = RAM::new_from_str("").unwrap();
+        assert_eq!(ram.backend, None);
+        let supported = cfg!(target_os = "macos") || cfg!(target_os = "linux");
+        assert_eq!(from_ram(&ram).is_ok(), supported);
+    }
+}
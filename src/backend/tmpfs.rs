@@ -0,0 +1,150 @@
+use crate::backend::{Backend, Usage};
+use crate::linuxcmd::{MemInfo, Mount, MountInfo};
+use crate::ram::RAM;
+use crate::runner::Runner;
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::PathBuf;
+
+pub struct Tmpfs {
+    runner: Box<dyn Runner>,
+    /// Where `/proc` is found
+    root: PathBuf,
+}
+
+impl Tmpfs {
+    pub fn new(runner: Box<dyn Runner>) -> Self {
+        Tmpfs {
+            runner,
+            root: PathBuf::from("/"),
+        }
+    }
+
+    /// Use `/proc` under `root` instead of the system's.
+    #[cfg(test)]
+    pub fn with_root<P: Into<PathBuf>>(mut self, root: P) -> Self {
+        self.root = root.into();
+        self
+    }
+
+    fn options(ram: &RAM, bytes: u64) -> String {
+        let mut options = vec![format!("size={}", bytes)];
+        if let Some(mode) = &ram.mode {
+            options.push(format!("mode={}", mode));
+        }
+        if let Some(uid) = ram.uid {
+            options.push(format!("uid={}", uid));
+        }
+        if let Some(nr_inodes) = &ram.nr_inodes {
+            options.push(format!("nr_inodes={}", nr_inodes));
+        }
+        options.join(",")
+    }
+}
+
+impl Backend for Tmpfs {
+    fn physical_memory(&self) -> Result<Option<u64>> {
+        MemInfo::total().map(Some)
+    }
+
+    fn create(&self, ram: &RAM, bytes: u64) -> Result<()> {
+        let mount_point = ram.volume_path();
+        fs::create_dir_all(&mount_point)
+            .with_context(|| format!("Failed to create {}", mount_point.to_string_lossy()))?;
+        Mount::new(&*self.runner).tmpfs(
+            &ram.name,
+            &Tmpfs::options(ram, bytes),
+            &mount_point.to_string_lossy(),
+        )
+    }
+
+    fn exists(&self, ram: &RAM) -> Result<bool> {
+        let mount_point = ram.volume_path();
+        let info = MountInfo::load_from(&self.root)?;
+        Ok(info.find(&mount_point.to_string_lossy()).is_some())
+    }
+
+    fn usage(&self, ram: &RAM) -> Result<Usage> {
+        Usage::of(ram.volume_path())
+    }
+
+    fn destroy(&self, ram: &RAM) -> Result<()> {
+        let mount_point = ram.volume_path();
+        Mount::new(&*self.runner).umount(&mount_point.to_string_lossy())?;
+        fs::remove_dir(&mount_point)
+            .with_context(|| format!("Failed to remove {}", mount_point.to_string_lossy()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::ScriptedRunner;
+    use std::rc::Rc;
+    use tempdir::TempDir;
+
+    #[test]
+    fn options() {
+        let ram = RAM::new_from_str("").unwrap();
+        assert_eq!(Tmpfs::options(&ram, 1048576), "size=1048576");
+
+        let ram = RAM::new_from_str(
+            r#"
+                mode = "0700"
+                uid = 1000
+                nr_inodes = "1m"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            Tmpfs::options(&ram, 1048576),
+            "size=1048576,mode=0700,uid=1000,nr_inodes=1m"
+        );
+    }
+
+    #[test]
+    fn replay_create_exists_and_destroy() {
+        let root = TempDir::new("ramup").unwrap();
+        fs::create_dir_all(root.path().join("proc/self")).unwrap();
+        let mountinfo = root.path().join("proc/self/mountinfo");
+        fs::write(&mountinfo, "").unwrap();
+        let toml = format!(
+            r#"
+                name = "RAMDisk"
+                backend = "tmpfs"
+                mode = "0700"
+                mount_path = "{}"
+            "#,
+            root.path().join("mnt").display()
+        );
+        let ram = RAM::new_from_str(&toml).unwrap();
+        let mount_point = ram.volume_path();
+        let mount_str = mount_point.to_string_lossy().to_string();
+        let runner = Rc::new(
+            ScriptedRunner::new()
+                .ok(
+                    &format!(
+                        "mount -t tmpfs -o size=1048576,mode=0700 RAMDisk {}",
+                        mount_str
+                    ),
+                    "",
+                )
+                .ok(&format!("umount {}", mount_str), ""),
+        );
+        let tmpfs = Tmpfs::new(Box::new(runner.clone())).with_root(root.path());
+
+        assert!(!tmpfs.exists(&ram).unwrap());
+        tmpfs.create(&ram, 1048576).unwrap();
+        assert!(mount_point.is_dir());
+
+        let line = format!(
+            "36 22 0:32 / {} rw,relatime shared:20 - tmpfs RAMDisk rw,size=1024k\n",
+            mount_str
+        );
+        fs::write(&mountinfo, line).unwrap();
+        assert!(tmpfs.exists(&ram).unwrap());
+        tmpfs.destroy(&ram).unwrap();
+        assert!(!mount_point.exists());
+        assert!(runner.is_finished());
+    }
+}
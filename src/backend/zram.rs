@@ -0,0 +1,206 @@
+use crate::backend::{Backend, Usage};
+use crate::linuxcmd::{MemInfo, Mkfs, Mount, MountInfo, Zram as ZramDevice};
+use crate::ram::RAM;
+use crate::runner::Runner;
+use anyhow::{Context, Result};
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+
+/// Compressed RAMDisk on a zram device formatted as ext4.
+pub struct Zram {
+    runner: Box<dyn Runner>,
+    /// Where `/sys` and `/proc` are found
+    root: PathBuf,
+}
+
+impl Zram {
+    pub fn new(runner: Box<dyn Runner>) -> Self {
+        Zram {
+            runner,
+            root: PathBuf::from("/"),
+        }
+    }
+
+    /// Use `/sys` and `/proc` under `root` instead of the system's.
+    #[cfg(test)]
+    pub fn with_root<P: Into<PathBuf>>(mut self, root: P) -> Self {
+        self.root = root.into();
+        self
+    }
+
+    /// Owner of the volume root: `uid` from `[ram]` with its primary group,
+    /// or the user running ramup.
+    fn root_owner(ram: &RAM) -> (u32, u32) {
+        let uid = ram.uid.unwrap_or_else(|| unsafe { libc::getuid() });
+        let passwd = unsafe { libc::getpwuid(uid) };
+        let gid = if passwd.is_null() {
+            unsafe { libc::getgid() }
+        } else {
+            unsafe { (*passwd).pw_gid }
+        };
+        (uid, gid)
+    }
+
+    fn device(&self, ram: &RAM) -> Result<ZramDevice> {
+        let mount_point = ram.volume_path();
+        let info = MountInfo::load_from(&self.root)?;
+        let entry = info
+            .find(&mount_point.to_string_lossy())
+            .with_context(|| format!("Not mounted: {}", mount_point.to_string_lossy()))?;
+        ZramDevice::from_device(&self.root, &entry.source)
+    }
+}
+
+impl Backend for Zram {
+    fn physical_memory(&self) -> Result<Option<u64>> {
+        MemInfo::total().map(Some)
+    }
+
+    fn create(&self, ram: &RAM, bytes: u64) -> Result<()> {
+        let device = ZramDevice::add(&self.root)?;
+        let created = || -> Result<()> {
+            if let Some(compression) = &ram.compression {
+                device.set_comp_algorithm(compression)?;
+            }
+            device.set_disksize(bytes)?;
+            Mkfs::new(&*self.runner).ext4(&ram.name, &device.device(), Zram::root_owner(ram))?;
+
+            let mount_point = ram.volume_path();
+            fs::create_dir_all(&mount_point)
+                .with_context(|| format!("Failed to create {}", mount_point.to_string_lossy()))?;
+            Mount::new(&*self.runner).device(&device.device(), &mount_point.to_string_lossy())?;
+            if let Some(mode) = &ram.mode {
+                let mode = u32::from_str_radix(mode, 8)
+                    .with_context(|| format!("Invalid mode: {}", mode))?;
+                fs::set_permissions(&mount_point, fs::Permissions::from_mode(mode))?;
+            }
+            Ok(())
+        }();
+        if created.is_err() {
+            let _ = device.remove();
+        }
+        created
+    }
+
+    fn exists(&self, ram: &RAM) -> Result<bool> {
+        let mount_point = ram.volume_path();
+        let info = MountInfo::load_from(&self.root)?;
+        Ok(info.find(&mount_point.to_string_lossy()).is_some())
+    }
+
+    fn usage(&self, ram: &RAM) -> Result<Usage> {
+        let stat = self.device(ram)?.stat()?;
+        let mut usage = Usage::of(ram.volume_path())?;
+        usage.compressed = Some(stat.compr_data_size);
+        usage.uncompressed = Some(stat.orig_data_size);
+        Ok(usage)
+    }
+
+    fn destroy(&self, ram: &RAM) -> Result<()> {
+        let device = self.device(ram)?;
+        let mount_point = ram.volume_path();
+        Mount::new(&*self.runner).umount(&mount_point.to_string_lossy())?;
+        device.remove()?;
+        fs::remove_dir(&mount_point)
+            .with_context(|| format!("Failed to remove {}", mount_point.to_string_lossy()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::ScriptedRunner;
+    use std::path::Path;
+    use std::rc::Rc;
+    use tempdir::TempDir;
+
+    /// Temporary `/sys` with zram1 ready to be added, and RAMDisk mounted under it
+    fn sysfs(root: &Path) -> RAM {
+        let control = root.join("sys/class/zram-control");
+        fs::create_dir_all(&control).unwrap();
+        fs::create_dir_all(root.join("sys/block/zram1")).unwrap();
+        fs::create_dir_all(root.join("proc/self")).unwrap();
+        fs::write(control.join("hot_add"), "1\n").unwrap();
+        fs::write(root.join("proc/self/mountinfo"), "").unwrap();
+        let toml = format!(
+            r#"
+                name = "RAMDisk"
+                backend = "zram"
+                compression = "zstd"
+                mode = "0700"
+                mount_path = "{}"
+            "#,
+            root.join("mnt").display()
+        );
+        RAM::new_from_str(&toml).unwrap()
+    }
+
+    fn mkfs(ram: &RAM) -> String {
+        let (uid, gid) = Zram::root_owner(ram);
+        format!(
+            "mkfs.ext4 -q -L RAMDisk -E root_owner={}:{} /dev/zram1",
+            uid, gid
+        )
+    }
+
+    fn mount(root: &Path, mount_point: &Path) {
+        let line = format!(
+            "36 22 252:1 / {} rw,relatime shared:20 - ext4 /dev/zram1 rw\n",
+            mount_point.display()
+        );
+        fs::write(root.join("proc/self/mountinfo"), line).unwrap();
+    }
+
+    #[test]
+    fn replay_create_exists_and_destroy() {
+        let root = TempDir::new("ramup").unwrap();
+        let ram = sysfs(root.path());
+        let mount_point = ram.volume_path();
+        let mount_str = mount_point.to_string_lossy().to_string();
+        let runner = Rc::new(
+            ScriptedRunner::new()
+                .ok(&mkfs(&ram), "")
+                .ok(&format!("mount /dev/zram1 {}", mount_str), "")
+                .ok(&format!("umount {}", mount_str), ""),
+        );
+        let zram = Zram::new(Box::new(runner.clone())).with_root(root.path());
+        let sys_path = root.path().join("sys/block/zram1");
+
+        assert!(!zram.exists(&ram).unwrap());
+        zram.create(&ram, 1048576).unwrap();
+        assert_eq!(
+            fs::read_to_string(sys_path.join("comp_algorithm")).unwrap(),
+            "zstd"
+        );
+        assert_eq!(
+            fs::read_to_string(sys_path.join("disksize")).unwrap(),
+            "1048576"
+        );
+        assert!(mount_point.is_dir());
+        let mode = fs::metadata(&mount_point).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+
+        mount(root.path(), &mount_point);
+        assert!(zram.exists(&ram).unwrap());
+        zram.destroy(&ram).unwrap();
+        assert_eq!(fs::read_to_string(sys_path.join("reset")).unwrap(), "1");
+        let hot_remove = root.path().join("sys/class/zram-control/hot_remove");
+        assert_eq!(fs::read_to_string(hot_remove).unwrap(), "1");
+        assert!(!mount_point.exists());
+        assert!(runner.is_finished());
+    }
+
+    #[test]
+    fn replay_create_failure_removes_device() {
+        let root = TempDir::new("ramup").unwrap();
+        let ram = sysfs(root.path());
+        let runner = Rc::new(ScriptedRunner::new().fail(&mkfs(&ram), "mkfs failed"));
+        let zram = Zram::new(Box::new(runner.clone())).with_root(root.path());
+
+        assert!(zram.create(&ram, 1048576).is_err());
+        let reset = root.path().join("sys/block/zram1/reset");
+        assert_eq!(fs::read_to_string(reset).unwrap(), "1");
+        assert!(runner.is_finished());
+    }
+}
@@ -2,7 +2,10 @@ use crate::appenv;
 use crate::application::Application;
 use crate::ram::RAM;
 use anyhow::Result;
+use serde::de::value::{MapAccessDeserializer, SeqAccessDeserializer};
+use serde::de::{Deserializer, MapAccess, SeqAccess, Visitor};
 use serde::Deserialize;
+use std::fmt::Formatter;
 use std::fs;
 use std::fs::File;
 use std::io::Write;
@@ -17,12 +20,75 @@ size = 8388608
 # name = "example"
 "#;
 
-#[derive(Debug, Deserialize, Default)]
+#[derive(Debug, Deserialize)]
 #[serde(default)]
 pub struct Config {
-    pub ram: RAM,
+    /// `[ram]` for a single RAMDisk or `[[ram]]` for multiple named RAMDisks
+    #[serde(deserialize_with = "one_or_many")]
+    pub ram: Vec<RAM>,
     #[serde(alias = "application")]
     pub applications: Vec<Application>,
+    pub sync: SyncConfig,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            ram: vec![RAM::default()],
+            applications: vec![],
+            sync: SyncConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct SyncConfig {
+    /// Persistent directory that `ramup sync` mirrors the RAMDisk into
+    pub path: String,
+    /// Minutes between syncs by `ramup daemon`
+    pub interval: u64,
+}
+
+impl Default for SyncConfig {
+    fn default() -> Self {
+        SyncConfig {
+            path: String::from("~/.config/ramup/backup"),
+            interval: 5,
+        }
+    }
+}
+
+struct RAMVisitor;
+
+impl<'de> Visitor<'de> for RAMVisitor {
+    type Value = Vec<RAM>;
+
+    fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
+        formatter.write_str("a [ram] table or [[ram]] array of tables")
+    }
+
+    fn visit_map<V>(self, map: V) -> Result<Vec<RAM>, V::Error>
+    where
+        V: MapAccess<'de>,
+    {
+        let ram = RAM::deserialize(MapAccessDeserializer::new(map))?;
+        Ok(vec![ram])
+    }
+
+    fn visit_seq<V>(self, seq: V) -> Result<Vec<RAM>, V::Error>
+    where
+        V: SeqAccess<'de>,
+    {
+        Vec::deserialize(SeqAccessDeserializer::new(seq))
+    }
+}
+
+fn one_or_many<'de, D>(deserializer: D) -> Result<Vec<RAM>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_any(RAMVisitor)
 }
 
 impl Config {
@@ -57,6 +123,7 @@ impl Config {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::size::Size;
     use serial_test::serial;
     use std::fs::File;
     use std::io::prelude::*;
@@ -77,9 +144,76 @@ mod tests {
         let mut contents = String::new();
         file.read_to_string(&mut contents).unwrap();
         let config: Config = toml::from_str(&contents).unwrap();
-        assert_eq!(config.ram.name, "RAMDiskByRamup");
-        assert_eq!(config.ram.size, 8388608);
+        assert_eq!(config.ram[0].name, "RAMDiskByRamup");
+        assert_eq!(config.ram[0].size, Size::Sectors(8388608));
 
         std::env::remove_var(appenv::KEY_CONFIG_PATH);
     }
+
+    #[test]
+    fn multiple_ram() {
+        let config: Config = toml::from_str(
+            r#"
+                [[ram]]
+                name = "cache"
+                size = "1GiB"
+
+                [[ram]]
+                name = "build"
+                size = "8GiB"
+
+                [[application]]
+                name = "my_build"
+                disk = "build"
+                paths = ["~/build"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.ram.len(), 2);
+        assert_eq!(config.ram[0].name, "cache");
+        assert_eq!(config.ram[1].size, Size::Bytes(8 << 30));
+        assert_eq!(config.applications[0].disk, Some("build".to_string()));
+
+        let config: Config = toml::from_str("").unwrap();
+        assert_eq!(config.ram.len(), 1);
+
+        assert!(toml::from_str::<Config>(
+            r#"
+                [ram]
+                size = "a lot"
+            "#
+        )
+        .is_err());
+
+        assert!(toml::from_str::<Config>(
+            r#"
+                [[application]]
+                name = "my_build"
+                disk = 1
+            "#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn sync_interval() {
+        let config: Config = toml::from_str(
+            r#"
+                [[application]]
+                name = "my_build"
+                sync_interval = 5
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.applications[0].sync_interval, Some(5));
+
+        assert!(toml::from_str::<Config>(
+            r#"
+                [[application]]
+                name = "my_build"
+                sync_interval = "5"
+            "#
+        )
+        .is_err());
+    }
 }
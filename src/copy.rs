@@ -0,0 +1,298 @@
+use crate::apperror::Interrupted;
+use crate::interrupt;
+use anyhow::{Context, Result};
+use filetime::FileTime;
+use indicatif::ProgressBar;
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::fs::{self, File, FileType, Metadata, Permissions};
+use std::io::{self, Read, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::{FileTypeExt, MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+
+/// Bytes copied between two checks for Ctrl-C
+const CHUNK: usize = 1 << 20;
+
+/// Copies a tree the way applications expect to find it again: permission bits, ownership,
+/// access and modification times and extended attributes are kept,
+/// symbolic links stay links, files hard linked inside the tree stay linked
+/// and FIFOs, sockets and devices are recreated without being opened.
+pub struct Copier<'a> {
+    pb: &'a ProgressBar,
+    /// Bytes copied so far
+    copied: u64,
+    /// First copy of each file with several links, by device and inode
+    links: HashMap<(u64, u64), PathBuf>,
+    interrupted: Box<dyn Fn() -> bool + 'a>,
+}
+
+impl<'a> Copier<'a> {
+    pub fn new(pb: &'a ProgressBar) -> Self {
+        Copier {
+            pb,
+            copied: 0,
+            links: HashMap::new(),
+            interrupted: Box::new(interrupt::is_interrupted),
+        }
+    }
+
+    /// Stop when `interrupted` returns true instead of on Ctrl-C.
+    #[cfg(test)]
+    pub fn with_interrupted(mut self, interrupted: impl Fn() -> bool + 'a) -> Self {
+        self.interrupted = Box::new(interrupted);
+        self
+    }
+
+    /// Copy `from` to `to`, overwriting files which already exist.
+    /// Fails with `Interrupted` on Ctrl-C, checked between two chunks of a file,
+    /// after removing the partially copied file.
+    pub fn copy(&mut self, from: &Path, to: &Path) -> Result<()> {
+        if (self.interrupted)() {
+            return Err(anyhow::anyhow!(Interrupted));
+        }
+
+        let meta = fs::symlink_metadata(from)
+            .with_context(|| format!("Failed to get metadata: {}", from.display()))?;
+        if meta.file_type().is_symlink() {
+            Copier::remove_file(to)?;
+            std::os::unix::fs::symlink(fs::read_link(from)?, to)?;
+        } else if is_special(&meta.file_type()) {
+            Copier::remove_file(to)?;
+            Copier::make_node(to, &meta)
+                .with_context(|| format!("Failed to create {}", to.display()))?;
+        } else if meta.is_dir() {
+            fs::create_dir_all(to)?;
+            for entry in fs::read_dir(from)? {
+                let entry = entry?;
+                self.copy(&entry.path(), &to.join(entry.file_name()))?;
+            }
+        } else if let Some(first) = self.first_link(&meta, to) {
+            // The link shares its metadata with the first copy
+            Copier::remove_file(to)?;
+            fs::hard_link(first, to)?;
+            self.copied += meta.len();
+            self.pb.set_position(self.copied);
+            return Ok(());
+        } else {
+            Copier::remove_file(to)?;
+            if let Err(err) = self.copy_file(from, to) {
+                if err.downcast_ref::<Interrupted>().is_some() {
+                    fs::remove_file(to)?;
+                }
+                return Err(err);
+            }
+        }
+        Copier::preserve(from, to, &meta)
+            .with_context(|| format!("Failed to keep metadata of {}", from.display()))
+    }
+
+    fn copy_file(&mut self, from: &Path, to: &Path) -> Result<()> {
+        let mut reader =
+            File::open(from).with_context(|| format!("Failed to open {}", from.display()))?;
+        let mut writer =
+            File::create(to).with_context(|| format!("Failed to create {}", to.display()))?;
+        let mut buf = vec![0; CHUNK];
+        loop {
+            let read = reader.read(&mut buf)?;
+            if read == 0 {
+                return Ok(());
+            }
+            writer.write_all(&buf[..read])?;
+            self.copied += read as u64;
+            self.pb.set_position(self.copied);
+            if (self.interrupted)() {
+                return Err(anyhow::anyhow!(Interrupted));
+            }
+        }
+    }
+
+    /// Where the file of `meta` was copied to already, or `None` after remembering `to` for it.
+    fn first_link(&mut self, meta: &Metadata, to: &Path) -> Option<PathBuf> {
+        if meta.nlink() < 2 {
+            return None;
+        }
+        let key = (meta.dev(), meta.ino());
+        if let Some(first) = self.links.get(&key) {
+            return Some(first.clone());
+        }
+        self.links.insert(key, to.to_path_buf());
+        None
+    }
+
+    /// Create the FIFO, socket or device file described by `meta` at `to`, as `cp -a` does.
+    fn make_node(to: &Path, meta: &Metadata) -> io::Result<()> {
+        let path = CString::new(to.as_os_str().as_bytes())?;
+        let mode = meta.mode() as libc::mode_t;
+        let ret = if meta.file_type().is_fifo() {
+            unsafe { libc::mkfifo(path.as_ptr(), mode & 0o7777) }
+        } else {
+            unsafe { libc::mknod(path.as_ptr(), mode, meta.rdev() as libc::dev_t) }
+        };
+        if ret != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    fn remove_file(path: &Path) -> Result<()> {
+        if fs::symlink_metadata(path).is_ok() {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    /// Give `to` the ownership, extended attributes, permissions and times of `from`.
+    /// Times come last since setting the others may change them.
+    fn preserve(from: &Path, to: &Path, meta: &Metadata) -> Result<()> {
+        let to_meta = fs::symlink_metadata(to)?;
+        if to_meta.uid() != meta.uid() || to_meta.gid() != meta.gid() {
+            // Only root can give files away; others keep their own files as `cp -p` does
+            ignore_unsupported(std::os::unix::fs::lchown(
+                to,
+                Some(meta.uid()),
+                Some(meta.gid()),
+            ))?;
+        }
+
+        for name in ignore_unsupported(xattr::list(from))?.into_iter().flatten() {
+            if let Some(value) = xattr::get(from, &name)? {
+                ignore_unsupported(xattr::set(to, &name, &value))?;
+            }
+        }
+
+        if !meta.file_type().is_symlink() {
+            fs::set_permissions(to, Permissions::from_mode(meta.mode()))?;
+        }
+        filetime::set_symlink_file_times(
+            to,
+            FileTime::from_last_access_time(meta),
+            FileTime::from_last_modification_time(meta),
+        )?;
+        Ok(())
+    }
+}
+
+/// FIFOs, sockets and devices, which block or fail when opened like a regular file
+pub fn is_special(file_type: &FileType) -> bool {
+    file_type.is_fifo()
+        || file_type.is_socket()
+        || file_type.is_block_device()
+        || file_type.is_char_device()
+}
+
+/// `None` when the file system or the user's privileges don't allow the operation.
+fn ignore_unsupported<T>(result: io::Result<T>) -> io::Result<Option<T>> {
+    match result {
+        Ok(t) => Ok(Some(t)),
+        Err(err) if err.raw_os_error() == Some(libc::ENOTSUP) => Ok(None),
+        Err(err) if err.raw_os_error() == Some(libc::EPERM) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn copy_keeps_metadata() {
+        let dir = TempDir::new("ramup").unwrap();
+        let from = dir.path().join("from");
+        fs::create_dir_all(from.join("sub")).unwrap();
+        fs::write(from.join("sub/file"), "contents").unwrap();
+        fs::set_permissions(from.join("sub/file"), Permissions::from_mode(0o640)).unwrap();
+        fs::hard_link(from.join("sub/file"), from.join("link")).unwrap();
+        std::os::unix::fs::symlink("sub/file", from.join("symlink")).unwrap();
+        let xattrs = xattr::set(from.join("sub/file"), "user.ramup", b"value").is_ok();
+        fs::set_permissions(from.join("sub"), Permissions::from_mode(0o750)).unwrap();
+        let then = FileTime::from_unix_time(1_000_000_000, 0);
+        for path in &["sub/file", "sub", "symlink"] {
+            filetime::set_symlink_file_times(from.join(path), then, then).unwrap();
+        }
+
+        let to = dir.path().join("to");
+        let pb = ProgressBar::hidden();
+        Copier::new(&pb).copy(&from, &to).unwrap();
+
+        let file = fs::metadata(to.join("sub/file")).unwrap();
+        assert_eq!(fs::read_to_string(to.join("sub/file")).unwrap(), "contents");
+        assert_eq!(file.mode() & 0o777, 0o640);
+        assert_eq!(FileTime::from_last_modification_time(&file), then);
+        assert_eq!(FileTime::from_last_access_time(&file), then);
+
+        let sub = fs::metadata(to.join("sub")).unwrap();
+        assert_eq!(sub.mode() & 0o777, 0o750);
+        assert_eq!(FileTime::from_last_modification_time(&sub), then);
+
+        assert_eq!(fs::metadata(to.join("link")).unwrap().ino(), file.ino());
+        assert_eq!(file.nlink(), 2);
+
+        let symlink = fs::symlink_metadata(to.join("symlink")).unwrap();
+        assert!(symlink.file_type().is_symlink());
+        assert_eq!(
+            fs::read_link(to.join("symlink")).unwrap(),
+            Path::new("sub/file")
+        );
+        assert_eq!(FileTime::from_last_modification_time(&symlink), then);
+
+        if xattrs {
+            assert_eq!(
+                xattr::get(to.join("sub/file"), "user.ramup").unwrap(),
+                Some(b"value".to_vec())
+            );
+        }
+    }
+
+    #[test]
+    fn copy_fifo_and_socket() {
+        let dir = TempDir::new("ramup").unwrap();
+        let from = dir.path().join("from");
+        fs::create_dir(&from).unwrap();
+        let fifo = CString::new(from.join("fifo").as_os_str().as_bytes()).unwrap();
+        assert_eq!(unsafe { libc::mkfifo(fifo.as_ptr(), 0o600) }, 0);
+        let _socket = std::os::unix::net::UnixListener::bind(from.join("socket")).unwrap();
+
+        let to = dir.path().join("to");
+        let pb = ProgressBar::hidden();
+        Copier::new(&pb).copy(&from, &to).unwrap();
+
+        let fifo = fs::symlink_metadata(to.join("fifo")).unwrap();
+        assert!(fifo.file_type().is_fifo());
+        assert_eq!(fifo.mode() & 0o777, 0o600);
+        assert!(fs::symlink_metadata(to.join("socket"))
+            .unwrap()
+            .file_type()
+            .is_socket());
+        // Verifying doesn't open them either
+        let differ = crate::verify::Verify::Hash.compare(&from, &to).unwrap();
+        assert!(differ.is_empty());
+    }
+
+    #[test]
+    fn copy_interrupted_in_file() {
+        use std::cell::Cell;
+
+        let dir = TempDir::new("ramup").unwrap();
+        let from = dir.path().join("image");
+        fs::write(&from, vec![0u8; 3 * CHUNK]).unwrap();
+
+        // Once before the file, then after each chunk
+        let checks = Cell::new(0);
+        let interrupted = || {
+            checks.set(checks.get() + 1);
+            checks.get() > 2
+        };
+        let to = dir.path().join("copy");
+        let pb = ProgressBar::hidden();
+        let err = Copier::new(&pb)
+            .with_interrupted(interrupted)
+            .copy(&from, &to)
+            .unwrap_err();
+        assert!(err.downcast_ref::<Interrupted>().is_some());
+        assert_eq!(checks.get(), 3);
+        assert!(!to.exists());
+        assert_eq!(fs::metadata(&from).unwrap().len(), 3 * CHUNK as u64);
+    }
+}
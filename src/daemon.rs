@@ -0,0 +1,133 @@
+use crate::application::Application;
+use crate::apppath::AbsPath;
+use crate::handler::Handler;
+use crate::interrupt;
+use crate::lock::Lock;
+use anyhow::Result;
+use std::collections::HashMap;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const TICK: Duration = Duration::from_secs(1);
+
+/// Write-behind loop which mirrors backed up paths to persistent storage
+/// every interval and once more on SIGINT/SIGTERM.
+pub struct Daemon {
+    mirror_path: String,
+    interval: Duration,
+    intervals: Vec<(String, Duration)>,
+}
+
+impl Daemon {
+    /// `interval` is in minutes, overridden by each application's `sync_interval`.
+    pub fn new(mirror_path: &str, interval: u64, apps: &[Application]) -> Result<Self> {
+        let mut intervals = vec![];
+        for app in apps {
+            if let Some(minutes) = app.sync_interval {
+                for path in &app.paths {
+                    let path = AbsPath::new(path)?.to_string();
+                    intervals.push((path, Daemon::minutes(minutes)?));
+                }
+            }
+        }
+        Ok(Daemon {
+            mirror_path: mirror_path.to_string(),
+            interval: Daemon::minutes(interval)?,
+            intervals,
+        })
+    }
+
+    pub fn run(&self, handler: &mut Handler, disk: Option<&str>) -> Result<()> {
+        interrupt::install()?;
+        println!("Syncing RAMDisk to {}", self.mirror_path);
+
+        let mut due: HashMap<String, Instant> = HashMap::new();
+        while !interrupt::is_interrupted() {
+            // Let other ramup commands run between ticks
+            let lock = match Lock::try_acquire()? {
+                Some(lock) => lock,
+                None => {
+                    thread::sleep(TICK);
+                    continue;
+                }
+            };
+            handler.reload()?;
+            let now = Instant::now();
+            for path in handler.backup_paths(disk)? {
+                if due.get(&path).is_some_and(|due| *due > now) {
+                    continue;
+                }
+                self.sync(handler, &path);
+                due.insert(path.clone(), now + self.interval_of(&path));
+            }
+            drop(lock);
+            thread::sleep(TICK);
+        }
+
+        println!("Stopping, syncing every path before exit");
+        let _lock = Lock::acquire(Duration::from_secs(30))?;
+        handler.reload()?;
+        for path in handler.backup_paths(disk)? {
+            self.sync(handler, &path);
+        }
+        Ok(())
+    }
+
+    fn sync(&self, handler: &Handler, path: &str) {
+        if let Err(err) = handler.sync_path(path, &self.mirror_path) {
+            println!("Failed to sync {}: {:?}", path, err);
+        }
+    }
+
+    /// Interval of the application owning `path`, or the default one.
+    fn interval_of(&self, path: &str) -> Duration {
+        self.intervals
+            .iter()
+            .filter(|(app_path, _)| Path::new(path).starts_with(app_path))
+            .map(|(_, interval)| *interval)
+            .min()
+            .unwrap_or(self.interval)
+    }
+
+    fn minutes(minutes: u64) -> Result<Duration> {
+        if minutes == 0 {
+            anyhow::bail!("sync interval must be at least 1 minute");
+        }
+        Ok(Duration::from_secs(minutes * 60))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interval_of() {
+        let apps = vec![
+            Application {
+                name: "ide".to_string(),
+                paths: vec!["/this/is/ide".to_string()],
+                sync_interval: Some(1),
+                ..Default::default()
+            },
+            Application {
+                name: "browser".to_string(),
+                paths: vec!["/this/is/browser".to_string()],
+                ..Default::default()
+            },
+        ];
+        let daemon = Daemon::new("/mirror", 10, &apps).unwrap();
+
+        assert_eq!(daemon.interval_of("/this/is/ide"), Duration::from_secs(60));
+        assert_eq!(
+            daemon.interval_of("/this/is/ide/cache"),
+            Duration::from_secs(60)
+        );
+        assert_eq!(
+            daemon.interval_of("/this/is/browser"),
+            Duration::from_secs(600)
+        );
+        assert!(Daemon::new("/mirror", 0, &apps).is_err());
+    }
+}
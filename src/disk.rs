@@ -0,0 +1,119 @@
+use crate::apppath::AbsPath;
+use crate::backend::{self, Backend, Usage};
+use crate::ram::RAM;
+use crate::size::Size;
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+/// A configured RAMDisk together with the backend that manages it.
+pub struct Disk {
+    pub ram: RAM,
+    backend: Box<dyn Backend>,
+}
+
+impl Disk {
+    pub fn new(ram: RAM) -> Result<Self> {
+        let backend = backend::from_ram(&ram)?;
+        Ok(Disk::with_backend(ram, backend))
+    }
+
+    pub fn with_backend(ram: RAM, backend: Box<dyn Backend>) -> Self {
+        Disk { ram, backend }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.ram.name
+    }
+
+    pub fn volume_path(&self) -> PathBuf {
+        self.ram.volume_path()
+    }
+
+    /// Whether `path` is stored on this disk.
+    pub fn contains<P: AsRef<Path>>(&self, path: P) -> bool {
+        path.as_ref().starts_with(self.volume_path())
+    }
+
+    pub fn exists(&self) -> Result<bool> {
+        self.backend.exists(&self.ram)
+    }
+
+    pub fn usage(&self) -> Result<Usage> {
+        self.backend.usage(&self.ram)
+    }
+
+    pub fn mount(&self, sources: &[String]) -> Result<()> {
+        if self.exists()? {
+            return Ok(());
+        }
+        let size = match self.ram.size {
+            Size::Auto => {
+                let required = Disk::required_size(sources)?;
+                self.ram.size.resolve(required, self.ram.headroom)?
+            }
+            size => size,
+        };
+        let physical = self.backend.physical_memory()?;
+        let bytes = size.bytes(physical)?;
+        self.backend.create(&self.ram, bytes)
+    }
+
+    pub fn unmount(&self) -> Result<()> {
+        if !self.exists()? {
+            return Ok(());
+        }
+        self.backend.destroy(&self.ram)
+    }
+
+    fn required_size(sources: &[String]) -> Result<u64> {
+        let mut required = 0;
+        for source in sources {
+            let source = AbsPath::new(source)?;
+            match std::fs::symlink_metadata(&source) {
+                Ok(meta) if !meta.file_type().is_symlink() => {
+                    required += fs_extra::dir::get_size(&source)?;
+                }
+                _ => continue,
+            }
+        }
+        Ok(required)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempdir::TempDir;
+
+    #[test]
+    fn required_size() {
+        let dir = TempDir::new("ramup-source").unwrap();
+        let source = dir.path().join("source");
+        fs::create_dir(&source).unwrap();
+        fs::write(source.join("file.txt"), vec![0; 1024]).unwrap();
+        let link = dir.path().join("link");
+        std::os::unix::fs::symlink(&source, &link).unwrap();
+
+        let sources = vec![
+            source.to_string_lossy().to_string(),
+            link.to_string_lossy().to_string(),
+            dir.path().join("missing").to_string_lossy().to_string(),
+        ];
+        assert_eq!(Disk::required_size(&sources).unwrap(), 1024);
+    }
+
+    #[test]
+    fn contains() {
+        let ram = RAM::new_from_str(
+            r#"
+                name = "RAMDisk"
+                mount_path = "/Volumes"
+            "#,
+        )
+        .unwrap();
+        let disk = Disk::with_backend(ram, Box::new(backend::Directory {}));
+        assert!(disk.contains("/Volumes/RAMDisk/Users/ramup"));
+        assert!(!disk.contains("/Volumes/RAMDisk2/Users/ramup"));
+    }
+}
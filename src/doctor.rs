@@ -0,0 +1,50 @@
+use std::fmt;
+use std::path::PathBuf;
+
+/// Inconsistency between state, symbolic links and RAMDisk contents found by `ramup doctor`.
+#[derive(Debug, PartialEq)]
+pub enum Finding {
+    /// Symbolic link whose target on the RAMDisk is missing
+    Dangling { path: String, target: PathBuf },
+    /// State entry which is no longer a symbolic link
+    NotSymlink { path: String },
+    /// Symbolic link into the RAMDisk which state doesn't know about
+    Untracked { path: String },
+    /// RAMDisk entry which no symbolic link points to
+    Orphan { path: String, target: PathBuf },
+}
+
+impl Finding {
+    /// Fix applied by `ramup doctor --fix`
+    pub fn fix(&self) -> &'static str {
+        match self {
+            Finding::Dangling { .. } => "re-hydrate the target from the sync mirror",
+            Finding::NotSymlink { .. } => "forget",
+            Finding::Untracked { .. } => "re-adopt",
+            Finding::Orphan { .. } => "restore",
+        }
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Finding::Dangling { path, target } => write!(
+                f,
+                "dangling symbolic link: {} -> {}",
+                path,
+                target.display()
+            ),
+            Finding::NotSymlink { path } => {
+                write!(f, "backed up path is not a symbolic link: {}", path)
+            }
+            Finding::Untracked { path } => write!(f, "untracked symbolic link: {}", path),
+            Finding::Orphan { path, target } => write!(
+                f,
+                "orphaned RAMDisk entry: {} (from {})",
+                target.display(),
+                path
+            ),
+        }
+    }
+}
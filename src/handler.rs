@@ -1,104 +1,782 @@
 use crate::appenv;
 use crate::apperror;
+use crate::appfs;
+use crate::application::Application;
 use crate::apppath::AbsPath;
-use crate::maccmd::{DiskUtil, HdiUtil};
-use crate::ram::RAM;
-use crate::state::State;
-use crate::subcmd::{Backup, Restore};
+use crate::disk::Disk;
+use crate::doctor::Finding;
+use crate::interrupt;
+use crate::journal::{self, Journal, Operation, Step};
+use crate::manifest::Manifest;
+use crate::report::{self, Record};
+use crate::state::{Entry, State};
+use crate::status::{DiskStatus, PathStatus, Status};
+use crate::subcmd::{Backup, Mirror, Restore};
+use crate::verify::Verify;
 use anyhow::{Context, Result};
-use std::convert::TryFrom;
+use indicatif::HumanBytes;
 use std::path::Path;
+use std::time::Instant;
 
 pub struct Handler {
-    ram: RAM,
+    disks: Vec<Disk>,
     state: State,
+    journal: Journal,
+    apps: Vec<Application>,
+    verify: Verify,
 }
 
 impl Handler {
-    pub fn new(ram: RAM, state: State) -> Self {
-        Handler { ram, state }
+    pub fn new(disks: Vec<Disk>, state: State) -> Result<Self> {
+        if disks.is_empty() {
+            anyhow::bail!("No RAMDisk is configured");
+        }
+        for (i, disk) in disks.iter().enumerate() {
+            if disks[..i].iter().any(|d| d.name() == disk.name()) {
+                anyhow::bail!("RAMDisk name is duplicated: {}", disk.name());
+            }
+        }
+        Ok(Handler {
+            disks,
+            state,
+            journal: Journal::load()?,
+            apps: vec![],
+            verify: Verify::default(),
+        })
+    }
+
+    /// Applications whose paths are recorded as owned by them.
+    pub fn with_applications(mut self, apps: Vec<Application>) -> Self {
+        self.apps = apps;
+        self
     }
 
-    pub fn backup(&mut self, sources: Vec<String>) -> Result<()> {
-        Handler::mount(&self.ram)?;
+    /// Check each copy this way before removing the original.
+    pub fn with_verify(mut self, verify: Verify) -> Self {
+        self.verify = verify;
+        self
+    }
+
+    pub fn backup(&mut self, disk: Option<&str>, sources: Vec<String>) -> Result<()> {
+        let disk = self.disk(disk)?;
+        disk.mount(&sources)?;
 
-        let target_base_path = AbsPath::try_from(&self.ram.mount_path)?.join(&self.ram.name)?;
+        let target_base_path = AbsPath::new(disk.volume_path())?;
+        let mut skipped = vec![];
         for source in &sources {
-            let source = AbsPath::new(&source)?;
+            if interrupt::is_interrupted() {
+                return Err(anyhow::anyhow!(apperror::Interrupted));
+            }
+            let source = AbsPath::new(source)?;
             let target = target_base_path.join(&source)?;
 
-            match Backup::backup(&source, &target) {
-                Ok(path) => self.state.add(path),
+            let started = Instant::now();
+            let bytes = fs_extra::dir::get_size(&source).ok();
+            let result = Backup::backup(&source, &target, &mut self.journal, self.verify);
+            report::emit(&Record::new(
+                "backup",
+                source.to_string(),
+                Some(target.to_string()),
+                bytes,
+                started,
+                &result,
+            ))?;
+
+            match result {
+                Ok(path) => {
+                    let entry = self.entry(&path, &target);
+                    self.track(entry)?;
+                    self.journal.commit(&path)
+                }
                 Err(err) => {
+                    self.settle()?;
+                    if err.downcast_ref::<apperror::Interrupted>().is_some() {
+                        return Err(err);
+                    }
+                    if let Some(summary) = Handler::not_enough_space(&err) {
+                        if !report::is_json() {
+                            println!("Not enough space to backup: {}", summary);
+                        }
+                        skipped.push(summary);
+                        continue;
+                    }
+                    if Handler::verification_failed(&err) {
+                        if !report::is_json() {
+                            println!("Failed to backup, rolled back: {}", err);
+                        }
+                        continue;
+                    }
                     if err.downcast_ref::<apperror::FileProgressError>().is_some() {
-                        println!("Failed to backup: {:?}", err);
+                        if !report::is_json() {
+                            println!("Failed to backup: {:?}", err);
+                        }
                         self.state.remove(target)?;
                         continue;
                     }
                     if err.downcast_ref::<apperror::FileSystemError>().is_some() {
                         continue;
                     }
+                    if !report::is_json() {
+                        println!(
+                            "Failed to backup {}, rolled back: {:#}",
+                            source.to_string(),
+                            err
+                        );
+                    }
                     Ok(())
                 }
             }?;
         }
+        Handler::print_skipped(&skipped);
         Ok(())
     }
 
-    pub fn restore(&mut self, targets: Vec<String>) -> Result<()> {
-        let source_base_path = AbsPath::try_from(&self.ram.mount_path)?.join(&self.ram.name)?;
+    pub fn restore(&mut self, disk: Option<&str>, targets: Vec<String>) -> Result<()> {
+        let only = match disk {
+            Some(name) => Some(self.disk(Some(name))?.name().to_string()),
+            None => None,
+        };
 
+        let mut skipped = vec![];
         for target in &targets {
-            let source = source_base_path.join(&target)?;
-            let target = AbsPath::new(&target)?;
+            if interrupt::is_interrupted() {
+                return Err(anyhow::anyhow!(apperror::Interrupted));
+            }
+            let disk = match self.disk_of(target) {
+                Ok(disk) => disk,
+                Err(err) => {
+                    let result: Result<()> = Err(err);
+                    let record = Record::new(
+                        "restore",
+                        target.clone(),
+                        None,
+                        None,
+                        Instant::now(),
+                        &result,
+                    );
+                    report::emit(&record)?;
+                    if only.is_none() && !report::is_json() {
+                        println!("Failed to restore: {}", record.message.unwrap_or_default());
+                    }
+                    continue;
+                }
+            };
+            if only.as_deref().is_some_and(|name| name != disk.name()) {
+                continue;
+            }
+            let source = AbsPath::new(disk.volume_path())?.join(target)?;
+            let target = AbsPath::new(target)?;
+
+            let started = Instant::now();
+            let bytes = fs_extra::dir::get_size(&source).ok();
+            let result = Restore::restore(&source, &target, &mut self.journal, self.verify);
+            report::emit(&Record::new(
+                "restore",
+                source.to_string(),
+                Some(target.to_string()),
+                bytes,
+                started,
+                &result,
+            ))?;
 
-            match Restore::restore(&source, &target) {
-                Ok(target) => self.state.remove(target),
+            match result {
+                Ok(target) => {
+                    self.untrack(&target)?;
+                    self.journal.commit(&target)
+                }
                 Err(err) => {
+                    self.settle()?;
+                    if err.downcast_ref::<apperror::Interrupted>().is_some() {
+                        return Err(err);
+                    }
+                    if let Some(summary) = Handler::not_enough_space(&err) {
+                        if !report::is_json() {
+                            println!("Not enough space to restore: {}", summary);
+                        }
+                        skipped.push(summary);
+                        continue;
+                    }
+                    if Handler::verification_failed(&err) {
+                        if !report::is_json() {
+                            println!("Failed to restore, rolled back: {}", err);
+                        }
+                        continue;
+                    }
                     if err.downcast_ref::<apperror::FileProgressError>().is_some() {
-                        println!("Failed to restore: {:?}", err);
+                        if !report::is_json() {
+                            println!("Failed to restore: {:?}", err);
+                        }
                         continue;
                     }
                     if err.downcast_ref::<apperror::FileSystemError>().is_some() {
-                        self.state.remove(target)?;
+                        self.untrack(target)?;
                         continue;
                     }
                     Err(err)
                 }
             }?;
         }
+        Handler::print_skipped(&skipped);
         Ok(())
     }
 
-    pub fn clean(&self) -> Result<()> {
+    pub fn clean(&mut self, disk: Option<&str>) -> Result<()> {
+        if let Some(name) = disk {
+            let disk = self.disk(Some(name))?;
+            let paths: Vec<String> = self
+                .state
+                .paths()
+                .into_iter()
+                .filter(|path| self.disk_of(path).is_ok_and(|d| d.name() == disk.name()))
+                .collect();
+            Handler::unmount(disk)?;
+            for path in paths {
+                self.state.remove(path)?;
+            }
+            return Ok(());
+        }
+
         let sp = appenv::state();
         if Path::new(&sp).exists() {
             std::fs::remove_file(&sp).with_context(|| "Failed to delete state file")?;
         }
-        Handler::unmount(&self.ram)
+        for disk in &self.disks {
+            Handler::unmount(disk)?;
+        }
+        Ok(())
+    }
+
+    fn unmount(disk: &Disk) -> Result<()> {
+        let started = Instant::now();
+        let volume_path = disk.volume_path();
+        let bytes = fs_extra::dir::get_size(&volume_path).ok();
+        let result = disk.unmount();
+        report::emit(&Record::new(
+            "clean",
+            volume_path.to_string_lossy().to_string(),
+            None,
+            bytes,
+            started,
+            &result,
+        ))?;
+        result
     }
 
-    fn mount(ram: &RAM) -> Result<()> {
-        if HdiUtil::exist_volume(&ram.name)? {
+    /// Finish or undo relocations interrupted by a crash, as recorded in the journal.
+    /// Data which was being copied is moved back to where it came from;
+    /// once it has been moved, the operation is rolled forward.
+    pub fn recover(&mut self) -> Result<()> {
+        for (entry, rolled) in self.settle()? {
+            if !report::is_json() {
+                println!(
+                    "Recovered interrupted {:?} of {} ({})",
+                    entry.operation, entry.path, rolled
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Roll back or forward every relocation in the journal, returning what was done to each.
+    /// Also used right after a failed backup or restore, whose error is reported by the caller.
+    fn settle(&mut self) -> Result<Vec<(journal::Entry, &'static str)>> {
+        let mut settled = vec![];
+        for entry in self.journal.entries.clone() {
+            let rolled = self.recover_entry(&entry)?;
+            self.journal.commit(&entry.path)?;
+            settled.push((entry, rolled));
+        }
+        Ok(settled)
+    }
+
+    fn recover_entry(&mut self, entry: &journal::Entry) -> Result<&'static str> {
+        let path = Path::new(&entry.path);
+        let ram_path = Path::new(&entry.ram_path);
+        let is_symlink =
+            |p: &Path| std::fs::symlink_metadata(p).is_ok_and(|meta| meta.file_type().is_symlink());
+
+        match (entry.operation, entry.step) {
+            (_, Step::Planned) | (_, Step::Committed) => return Ok("nothing to do"),
+            (Operation::Backup, Step::Copying) => {
+                if std::fs::symlink_metadata(ram_path).is_ok() {
+                    appfs::merge(&ram_path, &path)?;
+                }
+                return Ok("rolled back");
+            }
+            (Operation::Backup, Step::Moved) | (Operation::Backup, Step::Linked) => {
+                if std::fs::symlink_metadata(path).is_err() {
+                    std::os::unix::fs::symlink(ram_path, path)?;
+                }
+                self.track(self.entry(path, ram_path))?;
+            }
+            (Operation::Restore, Step::Copying) => {
+                if !is_symlink(path) {
+                    if std::fs::symlink_metadata(path).is_ok() {
+                        appfs::merge(&path, &ram_path)?;
+                    }
+                    std::os::unix::fs::symlink(ram_path, path)?;
+                }
+                return Ok("rolled back");
+            }
+            (Operation::Restore, _) => self.untrack(path)?,
+        }
+        Ok("rolled forward")
+    }
+
+    /// Mirror every backed up path from its RAMDisk into `mirror_path`.
+    pub fn sync<P: AsRef<Path>>(&self, disk: Option<&str>, mirror_path: P) -> Result<()> {
+        for path in self.backup_paths(disk)? {
+            self.sync_path(&path, mirror_path.as_ref())?;
+        }
+        Ok(())
+    }
+
+    /// Mirror a single backed up `path` from its RAMDisk into `mirror_path`.
+    pub fn sync_path<P: AsRef<Path>>(&self, path: &str, mirror_path: P) -> Result<()> {
+        let disk = self.disk_of(path)?;
+        let source = AbsPath::new(disk.volume_path())?.join(path)?;
+        if !source.as_ref().exists() {
+            println!("Not found on RAMDisk: {}", path);
             return Ok(());
         }
-        let mountpoint = HdiUtil::attach(ram.size)?;
-        DiskUtil::erasevolume(&ram.name, &mountpoint)
+        let target = AbsPath::new(mirror_path)?.join(path)?;
+
+        let summary = Mirror::mirror(&source, &target)?;
+        println!("{}: {}", path, summary);
+        Ok(())
     }
 
-    fn unmount(ram: &RAM) -> Result<()> {
-        if !HdiUtil::exist_volume(&ram.name)? {
+    /// Recreate RAMDisks after a reboot and re-hydrate every backed up path
+    /// from `mirror_path`, or as an empty directory when it was never synced.
+    pub fn up<P: AsRef<Path>>(&self, disk: Option<&str>, mirror_path: P) -> Result<()> {
+        let mirror_path = AbsPath::new(mirror_path)?;
+        let paths = self.backup_paths(disk)?;
+
+        for disk in &self.disks {
+            let paths: Vec<&String> = paths
+                .iter()
+                .filter(|path| self.disk_of(path).is_ok_and(|d| d.name() == disk.name()))
+                .collect();
+            if paths.is_empty() {
+                continue;
+            }
+            let mut mirrors = vec![];
+            for path in &paths {
+                mirrors.push(mirror_path.join(path)?.to_string());
+            }
+            disk.mount(&mirrors)?;
+
+            for path in paths {
+                let target = AbsPath::new(disk.volume_path())?.join(path)?;
+                if target.as_ref().exists() {
+                    continue;
+                }
+                Handler::rehydrate(path, target.as_ref(), &mirror_path)?;
+            }
+        }
+
+        let dangling: Vec<&String> = paths
+            .iter()
+            .filter(|path| std::fs::metadata(path).is_err())
+            .collect();
+        if !dangling.is_empty() {
+            for path in &dangling {
+                println!("Symbolic link does not resolve: {}", path);
+            }
+            anyhow::bail!("{} path(s) could not be recovered", dangling.len());
+        }
+        Ok(())
+    }
+
+    /// Copy `path` back to `target` on its RAMDisk from `mirror_path`,
+    /// or create an empty directory when it was never synced.
+    fn rehydrate(path: &str, target: &Path, mirror_path: &AbsPath) -> Result<()> {
+        let mirror = mirror_path.join(path)?;
+        if mirror.as_ref().exists() {
+            let summary = Mirror::mirror(&mirror, target)?;
+            println!("{}: {}", path, summary);
+        } else {
+            std::fs::create_dir_all(target)?;
+            println!("{}: no mirror, created an empty directory", path);
+        }
+        Ok(())
+    }
+
+    /// Report inconsistencies between state, symbolic links and RAMDisk contents,
+    /// and repair them when `fix` is set.
+    /// Dangling symbolic links are re-hydrated from `mirror_path` like `up` does.
+    pub fn doctor<P: AsRef<Path>>(
+        &mut self,
+        disk: Option<&str>,
+        fix: bool,
+        mirror_path: P,
+    ) -> Result<()> {
+        let mirror_path = AbsPath::new(mirror_path)?;
+        for disk in self.disks(disk)? {
+            if !disk.exists()? {
+                println!(
+                    "RAMDisk {} is not mounted, run `ramup up` first",
+                    disk.name()
+                );
+            }
+        }
+
+        let findings = self.diagnose(disk)?;
+        if findings.is_empty() {
+            println!("No problems found");
             return Ok(());
         }
-        HdiUtil::detach_volume(&ram.name)
+        for finding in &findings {
+            if !fix {
+                println!("{} (fix: {})", finding, finding.fix());
+                continue;
+            }
+            match self.repair(finding, &mirror_path) {
+                Ok(()) => println!("{} (fixed: {})", finding, finding.fix()),
+                Err(err) => println!("{} (failed to {}: {:?})", finding, finding.fix(), err),
+            }
+        }
+        if !fix {
+            println!("Run `ramup doctor --fix` to apply the fixes");
+        }
+        Ok(())
+    }
+
+    pub fn diagnose(&self, disk: Option<&str>) -> Result<Vec<Finding>> {
+        let mut findings = vec![];
+        for path in self.backup_paths(disk)? {
+            // A path whose RAMDisk is unknown can't be a valid symbolic link either
+            if let Ok(disk) = self.disk_of(&path) {
+                if !disk.exists()? {
+                    continue;
+                }
+            }
+            match std::fs::symlink_metadata(&path) {
+                Ok(meta) if meta.file_type().is_symlink() => {
+                    if std::fs::metadata(&path).is_err() {
+                        let target = std::fs::read_link(&path)?;
+                        findings.push(Finding::Dangling { path, target });
+                    }
+                }
+                _ => findings.push(Finding::NotSymlink { path }),
+            }
+        }
+
+        for disk in self.disks(disk)? {
+            if !disk.exists()? {
+                continue;
+            }
+            let volume_path = disk.volume_path();
+            self.scan(&volume_path, &volume_path, &mut findings)?;
+        }
+        Ok(findings)
+    }
+
+    fn repair(&mut self, finding: &Finding, mirror_path: &AbsPath) -> Result<()> {
+        match finding {
+            Finding::Dangling { path, target } => Handler::rehydrate(path, target, mirror_path)?,
+            Finding::NotSymlink { path } => self.untrack(path)?,
+            Finding::Untracked { path } => {
+                let target = std::fs::read_link(path)?;
+                self.track(self.recorded_entry(path, target)?)?
+            }
+            Finding::Orphan { path, target } => {
+                if std::fs::symlink_metadata(path).is_ok() {
+                    anyhow::bail!("{} already exists", path);
+                }
+                let parent = Path::new(path).parent().with_context(|| "No parent path")?;
+                std::fs::create_dir_all(parent)?;
+                appfs::relocate(target, path, self.verify)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Walk `dir` on the RAMDisk mounted at `volume_path` down to relocated paths.
+    fn scan(&self, volume_path: &Path, dir: &Path, findings: &mut Vec<Finding>) -> Result<()> {
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            let target = entry.path();
+            let relative = target.strip_prefix(volume_path)?;
+            // Metadata of the volume itself such as .Trashes, .fseventsd or ext4's lost+found
+            let name = entry.file_name().to_string_lossy().to_string();
+            if dir == volume_path && (name.starts_with('.') || name == "lost+found") {
+                continue;
+            }
+            let path = Path::new("/").join(relative);
+            let path_str = path.to_string_lossy().to_string();
+
+            match std::fs::symlink_metadata(&path) {
+                Ok(meta) if meta.file_type().is_symlink() => {
+                    if std::fs::read_link(&path)? != target {
+                        findings.push(Finding::Orphan {
+                            path: path_str,
+                            target,
+                        });
+                    } else if !self.state.contains(&path_str) {
+                        findings.push(Finding::Untracked { path: path_str });
+                    }
+                }
+                Ok(meta) if meta.is_dir() && entry.file_type()?.is_dir() => {
+                    self.scan(volume_path, &target, findings)?
+                }
+                _ => findings.push(Finding::Orphan {
+                    path: path_str,
+                    target,
+                }),
+            }
+        }
+        Ok(())
+    }
+
+    /// Mount state and usage of each RAMDisk and the health of each backed up path.
+    pub fn status(&self, disk: Option<&str>) -> Result<Status> {
+        let mut status = Status::default();
+        for disk in self.disks(disk)? {
+            let mounted = disk.exists()?;
+            let usage = if mounted { Some(disk.usage()?) } else { None };
+            status.disks.push(DiskStatus {
+                name: disk.name().to_string(),
+                volume_path: disk.volume_path().to_string_lossy().to_string(),
+                mounted,
+                usage,
+            });
+        }
+
+        for path in self.backup_paths(disk)? {
+            let entry = self.state.entries.iter().find(|entry| entry.source == path);
+            let application = entry
+                .and_then(|entry| entry.application.clone())
+                .or_else(|| self.application_of(&path));
+            let disk = match self.disk_of(&path) {
+                Ok(disk) => disk,
+                Err(_) => {
+                    status.paths.push(PathStatus {
+                        disk: "-".to_string(),
+                        ram_path: entry.map(|e| e.target.clone()).unwrap_or_default(),
+                        path,
+                        size: None,
+                        application,
+                        healthy: false,
+                    });
+                    continue;
+                }
+            };
+            let ram_path = AbsPath::new(disk.volume_path())?.join(&path)?;
+            let size = if ram_path.as_ref().exists() {
+                Some(fs_extra::dir::get_size(&ram_path)?)
+            } else {
+                None
+            };
+            let healthy = size.is_some()
+                && std::fs::read_link(&path).is_ok_and(|link| link == ram_path.as_ref());
+
+            status.paths.push(PathStatus {
+                path,
+                disk: disk.name().to_string(),
+                ram_path: ram_path.to_string(),
+                size,
+                application,
+                healthy,
+            });
+        }
+        Ok(status)
+    }
+
+    /// Rebuild state from the symbolic links which point into mounted RAMDisks,
+    /// with the metadata recorded in their manifests.
+    pub fn rebuild_state(&mut self) -> Result<usize> {
+        let mut findings = vec![];
+        for disk in self.disks(None)? {
+            if !disk.exists()? {
+                continue;
+            }
+            let volume_path = disk.volume_path();
+            self.scan(&volume_path, &volume_path, &mut findings)?;
+        }
+
+        let mut rebuilt = 0;
+        for finding in findings {
+            if let Finding::Untracked { path } = finding {
+                let target = std::fs::read_link(&path)?;
+                self.track(self.recorded_entry(&path, target)?)?;
+                rebuilt += 1;
+            }
+        }
+        Ok(rebuilt)
+    }
+
+    /// Backed up paths, limited to the disk named `disk` if given.
+    pub fn backup_paths(&self, disk: Option<&str>) -> Result<Vec<String>> {
+        let only = match disk {
+            Some(name) => Some(self.disk(Some(name))?.name().to_string()),
+            None => None,
+        };
+        Ok(self
+            .state
+            .paths()
+            .into_iter()
+            .filter(|path| {
+                only.as_deref()
+                    .is_none_or(|name| self.disk_of(path).is_ok_and(|disk| disk.name() == name))
+            })
+            .collect())
+    }
+
+    /// Backed up paths known to state or to the manifest of a mounted RAMDisk,
+    /// limited to the disk named `disk` if given.
+    pub fn restorable_paths(&self, disk: Option<&str>) -> Result<Vec<String>> {
+        let mut paths = self.backup_paths(disk)?;
+        for disk in self.disks(disk)? {
+            for entry in Manifest::load(disk.volume_path())?.entries {
+                if !paths.contains(&entry.source) {
+                    paths.push(entry.source);
+                }
+            }
+        }
+        Ok(paths)
+    }
+
+    /// Record `entry` in state and in the manifest of the RAMDisk it was backed up to.
+    /// Every path tracked in state is on a RAMDisk, whether it was just backed up,
+    /// rolled forward by `recover` or found again by `rebuild_state`,
+    /// so the manifest is written here to keep both in agreement.
+    /// Entries taken from the manifest by `recorded_entry` are written back unchanged.
+    fn track(&mut self, entry: Entry) -> Result<()> {
+        Manifest::load(self.disk_of(&entry.source)?.volume_path())?.add(entry.clone())?;
+        self.state.add(entry)
+    }
+
+    /// Forget `path` in state and in every RAMDisk's manifest.
+    fn untrack<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        for disk in &self.disks {
+            Manifest::load(disk.volume_path())?.remove(&path)?;
+        }
+        self.state.remove(path)
+    }
+
+    /// Entry for `path` as recorded in the manifest when backed up to `target`,
+    /// or one with the metadata known right now.
+    fn recorded_entry<P: AsRef<Path>, Q: AsRef<Path>>(&self, path: P, target: Q) -> Result<Entry> {
+        let manifest = Manifest::load(self.disk_of(&path)?.volume_path())?;
+        match manifest.get(&path) {
+            Some(entry) if Path::new(&entry.target) == target.as_ref() => Ok(entry.clone()),
+            _ => Ok(self.entry(path, target)),
+        }
+    }
+
+    /// State entry for `path` backed up to `target`, with the metadata known right now.
+    fn entry<P: AsRef<Path>, Q: AsRef<Path>>(&self, path: P, target: Q) -> Entry {
+        let path = path.as_ref().to_string_lossy().to_string();
+        let mut entry = Entry::new(&path, &target);
+        entry.disk = self.disk_of(&path).ok().map(|disk| disk.name().to_string());
+        entry.application = self.application_of(&path);
+        entry.size = fs_extra::dir::get_size(target.as_ref()).ok();
+        entry
+    }
+
+    /// Name of the application whose paths contain `path`.
+    fn application_of(&self, path: &str) -> Option<String> {
+        self.apps
+            .iter()
+            .find(|app| {
+                app.paths.iter().any(|app_path| {
+                    AbsPath::new(app_path).is_ok_and(|p| Path::new(path).starts_with(p))
+                })
+            })
+            .map(|app| app.name.clone())
+    }
+
+    /// Pick up paths backed up by other ramup processes.
+    pub fn reload(&mut self) -> Result<()> {
+        self.state = State::load()?;
+        Ok(())
+    }
+
+    /// Disk named `name`, or all of them when no name is given.
+    fn disks(&self, name: Option<&str>) -> Result<Vec<&Disk>> {
+        match name {
+            Some(name) => Ok(vec![self.disk(Some(name))?]),
+            None => Ok(self.disks.iter().collect()),
+        }
+    }
+
+    /// Disk configured with `name`, or the first one when no name is given.
+    fn disk(&self, name: Option<&str>) -> Result<&Disk> {
+        match name {
+            Some(name) => self
+                .disks
+                .iter()
+                .find(|d| d.name() == name)
+                .with_context(|| format!("No RAMDisk named {}", name)),
+            None => Ok(&self.disks[0]),
+        }
+    }
+
+    /// Disk that `path` was backed up to: the one recorded in state,
+    /// or else the one its symbolic link points into.
+    fn disk_of<P: AsRef<Path>>(&self, path: P) -> Result<&Disk> {
+        let path = path.as_ref();
+        let recorded = self
+            .state
+            .entries
+            .iter()
+            .find(|entry| Path::new(&entry.source) == path)
+            .and_then(|entry| entry.disk.as_deref());
+        if let Some(name) = recorded {
+            return self.disk(Some(name));
+        }
+
+        let link = std::fs::read_link(path).ok();
+        link.and_then(|link| self.disks.iter().find(|d| d.contains(&link)))
+            .ok_or_else(|| {
+                anyhow::anyhow!(apperror::FileSystemError::UnknownDisk(
+                    path.to_string_lossy().to_string()
+                ))
+            })
+    }
+
+    fn verification_failed(err: &anyhow::Error) -> bool {
+        matches!(
+            err.downcast_ref::<apperror::FileSystemError>(),
+            Some(apperror::FileSystemError::VerificationFailed { .. })
+        )
+    }
+
+    fn not_enough_space(err: &anyhow::Error) -> Option<String> {
+        match err.downcast_ref::<apperror::FileSystemError>() {
+            Some(apperror::FileSystemError::NotEnoughSpace {
+                path,
+                required,
+                available,
+            }) => Some(format!(
+                "{} (needs {}, {} available)",
+                path,
+                HumanBytes(*required),
+                HumanBytes(*available)
+            )),
+            _ => None,
+        }
+    }
+
+    fn print_skipped(skipped: &[String]) {
+        if skipped.is_empty() || report::is_json() {
+            return;
+        }
+        println!("{} path(s) were skipped for lack of space:", skipped.len());
+        for summary in skipped {
+            println!("  {}", summary);
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ram::RAM;
     use serial_test::serial;
     use std::fs;
+    use std::path::PathBuf;
     use tempdir::TempDir;
 
     macro_rules! check {
@@ -110,6 +788,72 @@ mod tests {
         };
     }
 
+    /// Temporary state file, mount path for RAMDisks on the directory backend
+    /// and directory holding the paths to back up, all removed when dropped.
+    struct Fixture {
+        mount_dir: TempDir,
+        target_dir: TempDir,
+        _state_dir: TempDir,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            let state_dir = check!(TempDir::new("ramup-config"));
+            let state_path = state_dir.path().join("state.toml");
+            std::env::set_var(crate::appenv::KEY_STATE_PATH, state_path);
+            Fixture {
+                mount_dir: check!(TempDir::new("ramup-source")),
+                target_dir: check!(TempDir::new("ramup-target")),
+                _state_dir: state_dir,
+            }
+        }
+
+        /// RAMDisk named `name` on the directory backend
+        fn disk(&self, name: &str) -> Disk {
+            let toml = format!(
+                r#"
+                     name = "{}"
+                     backend = "directory"
+                     mount_path = "{}"
+                "#,
+                name,
+                self.mount_dir.path().display()
+            );
+            check!(Disk::new(check!(RAM::new_from_str(&toml))))
+        }
+
+        /// Handler on a single RAMDisk named "RAMDisk" with the current state
+        fn handler(&self) -> Handler {
+            check!(Handler::new(
+                vec![self.disk("RAMDisk")],
+                check!(State::load())
+            ))
+        }
+
+        /// Path named `name` which doesn't exist yet
+        fn path(&self, name: &str) -> String {
+            self.target_dir
+                .path()
+                .join(name)
+                .to_string_lossy()
+                .to_string()
+        }
+
+        /// Directory named `name` holding `file.txt`
+        fn target(&self, name: &str) -> String {
+            let path = self.path(name);
+            check!(fs::create_dir(&path));
+            check!(fs::write(Path::new(&path).join("file.txt"), "ramup"));
+            path
+        }
+
+        /// Where `path` lives once backed up to the RAMDisk named `disk`
+        fn moved(&self, disk: &str, path: &str) -> PathBuf {
+            let relative = Path::new(path).strip_prefix("/").unwrap();
+            self.disk(disk).volume_path().join(relative)
+        }
+    }
+
     #[test]
     #[serial]
     #[cfg(target_os = "macos")]
@@ -136,11 +880,11 @@ mod tests {
             mount_str
         );
         let ram = RAM::new_from_str(&toml).unwrap();
-        let state = State::load();
+        let state = check!(State::load());
 
         // Backup
-        let mut handler = Handler::new(ram, state);
-        check!(handler.backup(vec![target_str.to_string()]));
+        let mut handler = check!(Handler::new(vec![check!(Disk::new(ram))], state));
+        check!(handler.backup(None, vec![target_str.to_string()]));
         let m = check!(fs::symlink_metadata(target_str));
         assert_eq!(m.file_type().is_symlink(), true);
         assert_eq!(m.file_type().is_dir(), false);
@@ -152,12 +896,508 @@ mod tests {
         //        assert_eq!(sym_file_path, check!(fs::read_link(target_str)));
 
         // Restore
-        check!(handler.restore(vec![target_str.to_string()]));
+        check!(handler.restore(None, vec![target_str.to_string()]));
         let m = check!(fs::symlink_metadata(target_str));
         assert_eq!(m.file_type().is_symlink(), false);
         assert_eq!(m.file_type().is_dir(), true);
 
+        check!(handler.clean(None));
+    }
+
+    #[test]
+    #[serial]
+    fn replay_macos_lifecycle() {
+        use crate::backend::MacOS;
+        use crate::runner::ScriptedRunner;
+        use std::rc::Rc;
+
+        let fixture = Fixture::new();
+        let target = fixture.target("target");
+        let target_str = target.as_str();
+
+        let toml = format!(
+            r#"
+                 name = "RAMDiskByRamup"
+                 size = "4GiB"
+                 mount_path = "{}"
+            "#,
+            fixture.mount_dir.path().display()
+        );
         let ram = RAM::new_from_str(&toml).unwrap();
-        check!(Handler::unmount(&ram));
+        let volume = ram.volume_path().to_string_lossy().to_string();
+        let runner = Rc::new(
+            ScriptedRunner::new()
+                .ok(
+                    "hdiutil info -plist",
+                    include_str!("maccmd/fixtures/hdiutil_info.plist"),
+                )
+                .ok("sysctl -n hw.memsize", "17179869184\n")
+                .ok(
+                    "hdiutil attach -nomount ram://8388608",
+                    include_str!("maccmd/fixtures/hdiutil_attach.txt"),
+                )
+                .ok(
+                    "diskutil erasevolume HFS+ RAMDiskByRamup /dev/disk2",
+                    include_str!("maccmd/fixtures/diskutil_erasevolume.txt"),
+                )
+                .ok("diskutil unmount /dev/disk2", "")
+                .ok(
+                    &format!("diskutil mount -mountPoint {} /dev/disk2", volume),
+                    "",
+                )
+                .ok(
+                    "hdiutil info -plist",
+                    &include_str!("maccmd/fixtures/hdiutil_info_with_volume.plist")
+                        .replace("/Volumes/RAMDiskByRamup", &volume),
+                )
+                .ok(&format!("hdiutil detach -force {}", volume), ""),
+        );
+        let backend = Box::new(MacOS::new(Box::new(Rc::clone(&runner))));
+        let disk = Disk::with_backend(ram, backend);
+        let mut handler = check!(Handler::new(vec![disk], check!(State::load())));
+
+        check!(handler.backup(None, vec![target_str.to_string()]));
+        assert!(check!(fs::symlink_metadata(target_str))
+            .file_type()
+            .is_symlink());
+        assert!(check!(fs::read_link(target_str)).starts_with(&volume));
+
+        check!(handler.restore(None, vec![target_str.to_string()]));
+        assert!(check!(fs::symlink_metadata(target_str)).is_dir());
+
+        check!(handler.clean(None));
+        assert!(runner.is_finished());
+    }
+
+    #[test]
+    fn not_enough_space() {
+        let err = anyhow::anyhow!(apperror::FileSystemError::NotEnoughSpace {
+            path: "/this/is/path".into(),
+            required: 2048,
+            available: 1024,
+        });
+        assert_eq!(
+            Handler::not_enough_space(&err).unwrap(),
+            "/this/is/path (needs 2.00KB, 1.00KB available)"
+        );
+
+        let err = anyhow::anyhow!(apperror::FileSystemError::NotExist("/this/is/path".into()));
+        assert!(Handler::not_enough_space(&err).is_none());
+    }
+
+    #[test]
+    #[serial]
+    fn backup_and_restore_on_directory() {
+        let fixture = Fixture::new();
+        let target = fixture.target("target");
+        let moved = fixture.moved("RAMDisk", &target);
+        let volume_path = fixture.disk("RAMDisk").volume_path();
+
+        // Backup
+        let mut handler = fixture.handler();
+        check!(handler.backup(None, vec![target.clone()]));
+        let m = check!(fs::symlink_metadata(&target));
+        assert!(m.file_type().is_symlink());
+        assert_eq!(moved, check!(fs::read_link(&target)));
+        assert_eq!("ramup", check!(fs::read_to_string(moved.join("file.txt"))));
+        assert_eq!(check!(State::load()).paths(), vec![target.clone()]);
+
+        // Restore
+        check!(handler.restore(None, vec![target.clone()]));
+        let m = check!(fs::symlink_metadata(&target));
+        assert!(!m.file_type().is_symlink());
+        assert!(m.file_type().is_dir());
+        assert_eq!(
+            "ramup",
+            check!(fs::read_to_string(Path::new(&target).join("file.txt")))
+        );
+        assert_eq!(check!(State::load()).paths().len(), 0);
+
+        // Clean
+        check!(handler.clean(None));
+        assert!(!volume_path.exists());
+    }
+
+    #[test]
+    #[serial]
+    fn backup_records_entry_metadata() {
+        let fixture = Fixture::new();
+        let target = fixture.target("target");
+        let mut handler = fixture.handler().with_applications(vec![Application {
+            name: "my_app".to_string(),
+            paths: vec![target.clone()],
+            ..Default::default()
+        }]);
+        check!(handler.backup(None, vec![target.clone()]));
+
+        let entry = check!(State::load()).entries.remove(0);
+        assert_eq!(entry.source, target);
+        assert_eq!(
+            entry.target,
+            fixture.moved("RAMDisk", &target).to_string_lossy()
+        );
+        assert_eq!(entry.disk, Some("RAMDisk".to_string()));
+        assert_eq!(entry.application, Some("my_app".to_string()));
+        assert_eq!(entry.size, Some(5));
+        assert!(entry.backed_up_at.is_some());
+        check!(handler.clean(None));
+    }
+
+    #[test]
+    #[serial]
+    fn backup_with_hash_verify() {
+        let fixture = Fixture::new();
+        let target = fixture.target("target");
+        let moved = fixture.moved("RAMDisk", &target);
+        let mut handler = fixture.handler().with_verify(Verify::Hash);
+
+        check!(handler.backup(None, vec![target.clone()]));
+        assert_eq!(moved, check!(fs::read_link(&target)));
+        check!(handler.restore(None, vec![target.clone()]));
+        assert_eq!(
+            "ramup",
+            check!(fs::read_to_string(Path::new(&target).join("file.txt")))
+        );
+        check!(handler.clean(None));
+    }
+
+    #[test]
+    #[serial]
+    fn status_on_directory() {
+        let fixture = Fixture::new();
+        let target = fixture.target("target");
+        let mut handler = fixture.handler().with_applications(vec![Application {
+            name: "my_app".to_string(),
+            paths: vec![target.clone()],
+            ..Default::default()
+        }]);
+        let status = check!(handler.status(None));
+        assert!(!status.disks[0].mounted);
+        assert!(status.paths.is_empty());
+
+        check!(handler.backup(None, vec![target.clone()]));
+        let status = check!(handler.status(None));
+        assert!(status.disks[0].mounted);
+        assert_eq!(status.paths[0].path, target);
+        assert!(status.paths[0].healthy);
+        assert_eq!(status.paths[0].size, Some(5));
+        assert_eq!(status.paths[0].application, Some("my_app".to_string()));
+
+        check!(fs::remove_dir_all(fixture.moved("RAMDisk", &target)));
+        assert!(!check!(handler.status(None)).paths[0].healthy);
+        check!(handler.clean(None));
+    }
+
+    #[test]
+    #[serial]
+    fn sync_on_directory() {
+        let fixture = Fixture::new();
+        let target = fixture.target("target");
+        let moved = fixture.moved("RAMDisk", &target);
+        let mut handler = fixture.handler();
+        check!(handler.backup(None, vec![target.clone()]));
+
+        let mirror_tmp_dir = check!(TempDir::new("ramup-mirror"));
+        let mirrored = mirror_tmp_dir
+            .path()
+            .join(Path::new(&target).strip_prefix("/").unwrap());
+        check!(handler.sync(None, mirror_tmp_dir.path()));
+        assert_eq!(
+            "ramup",
+            check!(fs::read_to_string(mirrored.join("file.txt")))
+        );
+        check!(fs::write(moved.join("new.txt"), "new"));
+        check!(handler.sync(None, mirror_tmp_dir.path()));
+        assert_eq!("new", check!(fs::read_to_string(mirrored.join("new.txt"))));
+        check!(handler.clean(None));
+    }
+
+    #[test]
+    #[serial]
+    fn up_on_directory() {
+        let fixture = Fixture::new();
+        let target = fixture.target("target");
+        let moved = fixture.moved("RAMDisk", &target);
+        let volume_path = fixture.disk("RAMDisk").volume_path();
+        let mut handler = fixture.handler();
+        check!(handler.backup(None, vec![target.clone()]));
+        let mirror_tmp_dir = check!(TempDir::new("ramup-mirror"));
+        check!(handler.sync(None, mirror_tmp_dir.path()));
+
+        // The RAMDisk is gone after a reboot
+        check!(fs::write(moved.join("file.txt"), "lost"));
+        check!(fs::remove_dir_all(&volume_path));
+        assert!(fs::metadata(&target).is_err());
+        check!(handler.up(None, mirror_tmp_dir.path()));
+        assert_eq!("ramup", check!(fs::read_to_string(moved.join("file.txt"))));
+        assert_eq!(moved, check!(fs::read_link(&target)));
+
+        // Paths which were never synced come back empty
+        check!(fs::remove_dir_all(&volume_path));
+        let never_synced = check!(TempDir::new("ramup-mirror"));
+        check!(handler.up(None, never_synced.path()));
+        assert!(moved.is_dir());
+        assert!(!moved.join("file.txt").exists());
+        check!(handler.clean(None));
+    }
+
+    #[test]
+    #[serial]
+    fn restore_forgets_path_which_is_not_a_link() {
+        let fixture = Fixture::new();
+        let target = fixture.target("target");
+        let volume_path = fixture.disk("RAMDisk").volume_path();
+        let mut handler = fixture.handler();
+        check!(handler.backup(None, vec![target.clone()]));
+
+        // Put back by hand, so there is nothing left to restore
+        check!(fs::remove_file(&target));
+        check!(fs::create_dir(&target));
+        check!(handler.restore(None, vec![target.clone()]));
+        assert!(check!(State::load()).paths().is_empty());
+        assert!(check!(Manifest::load(&volume_path)).entries.is_empty());
+        assert!(check!(handler.restorable_paths(None)).is_empty());
+        check!(handler.clean(None));
+    }
+
+    #[test]
+    #[serial]
+    fn doctor_on_directory() {
+        let fixture = Fixture::new();
+        let (dangling, forgotten, untracked, orphan) = (
+            fixture.target("dangling"),
+            fixture.path("forgotten"),
+            fixture.target("untracked"),
+            fixture.path("orphan"),
+        );
+
+        let volume = AbsPath::new(fixture.disk("RAMDisk").volume_path()).unwrap();
+        let mut handler = fixture.handler();
+        check!(handler.backup(None, vec![dangling.clone(), untracked.clone()]));
+
+        check!(fs::remove_dir_all(volume.join(&dangling).unwrap()));
+        check!(handler.state.add(Entry::new(&forgotten, "")));
+        check!(handler.state.remove(&untracked));
+        let orphan_target = volume.join(&orphan).unwrap();
+        check!(fs::create_dir_all(&orphan_target));
+        check!(fs::write(orphan_target.as_ref().join("file.txt"), "ramup"));
+        // Created by mkfs.ext4 on zram, not relocated by ramup
+        check!(fs::create_dir_all(volume.as_ref().join("lost+found")));
+
+        let findings = check!(handler.diagnose(None));
+        assert_eq!(findings.len(), 4);
+        assert!(findings.contains(&Finding::Dangling {
+            path: dangling.clone(),
+            target: volume.join(&dangling).unwrap().as_ref().to_path_buf(),
+        }));
+        assert!(findings.contains(&Finding::NotSymlink {
+            path: forgotten.clone()
+        }));
+        assert!(findings.contains(&Finding::Untracked {
+            path: untracked.clone()
+        }));
+        assert!(findings.contains(&Finding::Orphan {
+            path: orphan.clone(),
+            target: orphan_target.as_ref().to_path_buf(),
+        }));
+
+        let mirror_tmp_dir = check!(TempDir::new("ramup-mirror"));
+        check!(handler.doctor(None, true, mirror_tmp_dir.path()));
+        assert_eq!(check!(handler.diagnose(None)), vec![]);
+        assert!(fs::metadata(&dangling).unwrap().is_dir());
+        assert_eq!(check!(State::load()).paths(), vec![dangling, untracked]);
+        assert_eq!(
+            "ramup",
+            check!(fs::read_to_string(Path::new(&orphan).join("file.txt")))
+        );
+        check!(handler.clean(None));
+    }
+
+    #[test]
+    #[serial]
+    fn doctor_then_sync_keeps_mirror() {
+        let fixture = Fixture::new();
+        let target = fixture.target("target");
+        let moved = fixture.moved("RAMDisk", &target);
+        let mut handler = fixture.handler();
+        check!(handler.backup(None, vec![target.clone()]));
+        let mirror_tmp_dir = check!(TempDir::new("ramup-mirror"));
+        let mirrored = mirror_tmp_dir
+            .path()
+            .join(Path::new(&target).strip_prefix("/").unwrap());
+        check!(handler.sync(None, mirror_tmp_dir.path()));
+
+        // The target on the RAMDisk is lost, the mirror is the only copy left
+        check!(fs::remove_dir_all(&moved));
+        check!(handler.doctor(None, true, mirror_tmp_dir.path()));
+        assert_eq!("ramup", check!(fs::read_to_string(moved.join("file.txt"))));
+
+        check!(handler.sync(None, mirror_tmp_dir.path()));
+        assert_eq!(
+            "ramup",
+            check!(fs::read_to_string(mirrored.join("file.txt")))
+        );
+        check!(handler.clean(None));
+    }
+
+    #[test]
+    #[serial]
+    fn recover_interrupted_relocations() {
+        let fixture = Fixture::new();
+        let target = fixture.target("target");
+        let target_path = Path::new(&target);
+        let moved = fixture.moved("RAMDisk", &target);
+        let mut handler = fixture.handler();
+        check!(handler.disks[0].mount(&[]));
+
+        // Killed while copying to RAMDisk: the partial copy is removed
+        check!(handler.journal.begin(Operation::Backup, &target, &moved));
+        check!(handler.journal.step(&target, Step::Copying));
+        check!(fs::create_dir_all(&moved));
+        check!(fs::write(moved.join("file.txt"), "ram"));
+        check!(handler.recover());
+        assert!(!moved.exists());
+        assert_eq!(
+            "ramup",
+            check!(fs::read_to_string(target_path.join("file.txt")))
+        );
+        assert_eq!(check!(State::load()).paths().len(), 0);
+
+        // Killed after moving: the symbolic link and state entry are created
+        check!(handler.journal.begin(Operation::Backup, &target, &moved));
+        check!(fs::rename(target_path, &moved));
+        check!(handler.journal.step(&target, Step::Moved));
+        check!(handler.recover());
+        assert_eq!(moved, check!(fs::read_link(&target)));
+        assert_eq!(check!(State::load()).paths(), vec![target.clone()]);
+
+        // Killed while copying back from RAMDisk: the symbolic link is restored
+        check!(handler.journal.begin(Operation::Restore, &target, &moved));
+        check!(handler.journal.step(&target, Step::Copying));
+        check!(fs::remove_file(target_path));
+        check!(fs::create_dir(target_path));
+        check!(handler.recover());
+        assert_eq!(moved, check!(fs::read_link(&target)));
+        assert_eq!("ramup", check!(fs::read_to_string(moved.join("file.txt"))));
+        assert!(Journal::load().unwrap().entries.is_empty());
+
+        check!(handler.restore(None, vec![target.clone()]));
+        check!(handler.clean(None));
+    }
+
+    #[test]
+    #[serial]
+    fn rebuild_state() {
+        let fixture = Fixture::new();
+        let target = fixture.target("target");
+        let volume_path = fixture.disk("RAMDisk").volume_path();
+        let mut handler = fixture.handler().with_applications(vec![Application {
+            name: "target".to_string(),
+            paths: vec![target.clone()],
+            ..Default::default()
+        }]);
+        check!(handler.backup(None, vec![target.clone()]));
+        let recorded = check!(Manifest::load(&volume_path)).entries;
+        assert_eq!(recorded, check!(State::load()).entries);
+
+        // Everything but the volume is forgotten
+        check!(State::set_aside());
+        let mut handler = check!(Handler::new(
+            vec![fixture.disk("RAMDisk")],
+            State::default()
+        ));
+        assert_eq!(check!(handler.restorable_paths(None)), vec![target.clone()]);
+        assert_eq!(check!(handler.rebuild_state()), 1);
+        assert_eq!(check!(State::load()).entries, recorded);
+        assert_eq!(check!(Manifest::load(&volume_path)).entries, recorded);
+
+        // Without the manifest either, the symbolic link is enough to record it again
+        check!(State::set_aside());
+        check!(fs::remove_file(
+            volume_path.join(crate::manifest::DIR).join("manifest.toml")
+        ));
+        let mut handler = check!(Handler::new(
+            vec![fixture.disk("RAMDisk")],
+            State::default()
+        ));
+        assert_eq!(check!(handler.rebuild_state()), 1);
+        let manifest = check!(Manifest::load(&volume_path)).entries;
+        assert_eq!(manifest, check!(State::load()).entries);
+        assert_eq!(manifest.len(), 1);
+        assert_eq!(manifest[0].source, target);
+        assert_eq!(manifest[0].disk.as_deref(), Some("RAMDisk"));
+
+        check!(handler.restore(None, vec![target]));
+        assert!(check!(Manifest::load(&volume_path)).entries.is_empty());
+        check!(handler.clean(None));
+    }
+
+    #[test]
+    #[serial]
+    fn backup_and_clean_on_multiple_disks() {
+        let fixture = Fixture::new();
+        let (cache, build) = (fixture.target("cache"), fixture.target("build"));
+
+        let disks = vec![fixture.disk("fast"), fixture.disk("large")];
+        let fast_volume = disks[0].volume_path();
+        let large_volume = disks[1].volume_path();
+        let mut handler = check!(Handler::new(disks, check!(State::load())));
+        assert!(handler.backup(Some("unknown"), vec![]).is_err());
+
+        check!(handler.backup(None, vec![cache.clone()]));
+        check!(handler.backup(Some("large"), vec![build.clone()]));
+        assert!(check!(fs::read_link(&cache)).starts_with(&fast_volume));
+        assert!(check!(fs::read_link(&build)).starts_with(&large_volume));
+
+        // Restore only from the large disk
+        let paths = check!(State::load()).paths();
+        check!(handler.restore(Some("large"), paths));
+        assert!(check!(fs::symlink_metadata(&cache))
+            .file_type()
+            .is_symlink());
+        assert!(check!(fs::symlink_metadata(&build)).is_dir());
+
+        // Clean only the fast disk
+        check!(handler.backup(Some("large"), vec![build.clone()]));
+        check!(handler.clean(Some("fast")));
+        assert!(!fast_volume.exists());
+        assert!(large_volume.exists());
+        assert_eq!(check!(State::load()).paths(), vec![build]);
+
+        check!(handler.clean(None));
+        assert!(!large_volume.exists());
+    }
+
+    #[test]
+    #[serial]
+    fn disk_of_recorded_disk() {
+        let fixture = Fixture::new();
+        let build = fixture.target("build");
+
+        let disks = vec![fixture.disk("fast"), fixture.disk("large")];
+        let mut handler = check!(Handler::new(disks, check!(State::load())));
+        check!(handler.backup(Some("large"), vec![build.clone()]));
+
+        // The disk recorded in state is used even without the symbolic link
+        check!(fs::remove_file(&build));
+        assert_eq!(check!(handler.disk_of(&build)).name(), "large");
+
+        let err = handler.disk_of(fixture.path("unknown")).err();
+        assert!(matches!(
+            err.as_ref()
+                .and_then(|err| err.downcast_ref::<apperror::FileSystemError>()),
+            Some(apperror::FileSystemError::UnknownDisk(_))
+        ));
+        check!(handler.clean(None));
+    }
+
+    #[test]
+    fn duplicated_disks() {
+        let disk = || {
+            let ram = check!(RAM::new_from_str(r#"backend = "directory""#));
+            check!(Disk::new(ram))
+        };
+        assert!(Handler::new(vec![], State::default()).is_err());
+        assert!(Handler::new(vec![disk(), disk()], State::default()).is_err());
     }
 }
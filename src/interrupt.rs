@@ -0,0 +1,28 @@
+use anyhow::Result;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Once;
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+static INSTALL: Once = Once::new();
+
+/// Exit status when ramup stops on SIGINT or SIGTERM, as a shell does for SIGINT.
+pub const EXIT_CODE: i32 = 130;
+
+/// Record SIGINT and SIGTERM instead of exiting immediately.
+/// Long running work polls `is_interrupted` and stops at a safe point.
+pub fn install() -> Result<()> {
+    let mut result = Ok(());
+    INSTALL.call_once(|| {
+        result = ctrlc::set_handler(|| INTERRUPTED.store(true, Ordering::SeqCst));
+    });
+    Ok(result?)
+}
+
+pub fn is_interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+#[cfg(test)]
+pub fn set_interrupted(interrupted: bool) {
+    INTERRUPTED.store(interrupted, Ordering::SeqCst);
+}
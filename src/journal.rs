@@ -0,0 +1,135 @@
+use crate::appenv;
+use crate::appfs;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Operation {
+    Backup,
+    Restore,
+}
+
+/// Progress of an operation.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Step {
+    Planned,
+    /// Written before the copy starts
+    Copying,
+    /// Written once the copy is complete and the original is removed
+    Moved,
+    /// Written once the symbolic link is in place
+    Linked,
+    Committed,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub operation: Operation,
+    /// Original path, which is the symbolic link while backed up
+    pub path: String,
+    /// Where `path` lives on the RAMDisk
+    pub ram_path: String,
+    pub step: Step,
+}
+
+/// Write-ahead journal of relocations that haven't been committed to `State` yet.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Journal {
+    pub entries: Vec<Entry>,
+}
+
+impl Journal {
+    pub fn load() -> Result<Self> {
+        let jp = appenv::journal();
+        if !Path::new(&jp).exists() {
+            return Ok(Journal::default());
+        }
+        let c = fs::read_to_string(&jp)?;
+        let journal: Journal =
+            toml::from_str(&c).with_context(|| format!("Failed to parse journal: {}", jp))?;
+        Ok(journal)
+    }
+
+    pub fn begin<P: AsRef<Path>, Q: AsRef<Path>>(
+        &mut self,
+        operation: Operation,
+        path: P,
+        ram_path: Q,
+    ) -> Result<()> {
+        let path = String::from(path.as_ref().to_string_lossy());
+        self.entries.retain(|e| e.path != path);
+        self.entries.push(Entry {
+            operation,
+            path,
+            ram_path: String::from(ram_path.as_ref().to_string_lossy()),
+            step: Step::Planned,
+        });
+        self.save()
+    }
+
+    pub fn step<P: AsRef<Path>>(&mut self, path: P, step: Step) -> Result<()> {
+        let path = path.as_ref().to_string_lossy();
+        if let Some(entry) = self.entries.iter_mut().find(|e| e.path == path) {
+            entry.step = step;
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    /// Mark the operation on `path` as committed and drop it from the journal.
+    pub fn commit<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        self.step(&path, Step::Committed)?;
+        let path = path.as_ref().to_string_lossy();
+        self.entries.retain(|e| e.path != path);
+        self.save()
+    }
+
+    fn save(&self) -> Result<()> {
+        let jp = appenv::journal();
+        if self.entries.is_empty() {
+            if Path::new(&jp).exists() {
+                fs::remove_file(&jp)?;
+            }
+            return Ok(());
+        }
+        appfs::write_atomic(&jp, &toml::to_string(&self)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serial_test::serial;
+    use tempdir::TempDir;
+
+    #[test]
+    #[serial]
+    fn begin_step_and_commit() {
+        let dir = TempDir::new("ramup").unwrap();
+        std::env::set_var(appenv::KEY_STATE_PATH, dir.path().join("state.toml"));
+
+        let mut journal = Journal::load().unwrap();
+        journal
+            .begin(
+                Operation::Backup,
+                "/this/is/path",
+                "/Volumes/RAMDisk/this/is/path",
+            )
+            .unwrap();
+        journal.step("/this/is/path", Step::Moved).unwrap();
+        assert!(dir.path().join("journal.toml").exists());
+
+        let mut journal = Journal::load().unwrap();
+        assert_eq!(journal.entries.len(), 1);
+        assert_eq!(journal.entries[0].operation, Operation::Backup);
+        assert_eq!(journal.entries[0].step, Step::Moved);
+
+        journal.commit("/this/is/path").unwrap();
+        assert_eq!(Journal::load().unwrap().entries.len(), 0);
+        assert!(!dir.path().join("journal.toml").exists());
+    }
+}
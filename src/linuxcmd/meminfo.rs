@@ -0,0 +1,46 @@
+use anyhow::{Context, Result};
+use std::fs;
+
+static MEMINFO_PATH: &str = "/proc/meminfo";
+
+pub struct MemInfo {}
+
+impl MemInfo {
+    pub fn total() -> Result<u64> {
+        let c = fs::read_to_string(MEMINFO_PATH)
+            .with_context(|| format!("Failed to read {}", MEMINFO_PATH))?;
+        MemInfo::parse_total(&c)
+    }
+
+    // MemTotal:       16318428 kB
+    fn parse_total(c: &str) -> Result<u64> {
+        let line = c
+            .lines()
+            .find(|l| l.starts_with("MemTotal:"))
+            .with_context(|| "MemTotal not found in meminfo")?;
+        let kb: u64 = line
+            .split_whitespace()
+            .nth(1)
+            .with_context(|| format!("Invalid meminfo line: {}", line))?
+            .parse()?;
+        Ok(kb * 1024)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_total() {
+        let meminfo = "MemTotal:       16318428 kB\nMemFree:         1234567 kB\n";
+        assert_eq!(MemInfo::parse_total(meminfo).unwrap(), 16318428 * 1024);
+        assert!(MemInfo::parse_total("MemFree: 1 kB").is_err());
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn total() {
+        assert!(MemInfo::total().unwrap() > 0);
+    }
+}
@@ -0,0 +1,27 @@
+use crate::runner::Runner;
+use anyhow::Result;
+
+pub struct Mkfs<'a> {
+    runner: &'a dyn Runner,
+}
+
+impl<'a> Mkfs<'a> {
+    pub fn new(runner: &'a dyn Runner) -> Self {
+        Mkfs { runner }
+    }
+
+    /// Format `device` with its root directory owned by `root_owner`, a uid and gid,
+    /// instead of by whoever runs `mkfs.ext4`.
+    pub fn ext4(&self, name: &str, device: &str, root_owner: (u32, u32)) -> Result<()> {
+        let root_owner = format!("root_owner={}:{}", root_owner.0, root_owner.1);
+        let output = self
+            .runner
+            .run("mkfs.ext4", &["-q", "-L", name, "-E", &root_owner, device])?;
+
+        if !output.status.success() {
+            anyhow::bail!("failed to mkfs.ext4 command: {:?}", output.stderr);
+        };
+
+        Ok(())
+    }
+}
@@ -0,0 +1,11 @@
+pub use meminfo::MemInfo;
+pub use mkfs::Mkfs;
+pub use mount::Mount;
+pub use mountinfo::MountInfo;
+pub use zram::Zram;
+
+mod meminfo;
+mod mkfs;
+mod mount;
+mod mountinfo;
+mod zram;
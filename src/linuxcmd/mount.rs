@@ -0,0 +1,72 @@
+use crate::runner::Runner;
+use anyhow::Result;
+
+pub struct Mount<'a> {
+    runner: &'a dyn Runner,
+}
+
+impl<'a> Mount<'a> {
+    pub fn new(runner: &'a dyn Runner) -> Self {
+        Mount { runner }
+    }
+
+    pub fn tmpfs(&self, name: &str, options: &str, mount_point: &str) -> Result<()> {
+        let mut args = vec!["-t", "tmpfs"];
+        if !options.is_empty() {
+            args.extend(&["-o", options]);
+        }
+        args.extend(&[name, mount_point]);
+        let output = self.runner.run("mount", &args)?;
+
+        if !output.status.success() {
+            anyhow::bail!("failed to mount command: {:?}", output.stderr);
+        };
+
+        Ok(())
+    }
+
+    pub fn device(&self, device: &str, mount_point: &str) -> Result<()> {
+        let output = self.runner.run("mount", &[device, mount_point])?;
+
+        if !output.status.success() {
+            anyhow::bail!("failed to mount command: {:?}", output.stderr);
+        };
+
+        Ok(())
+    }
+
+    pub fn umount(&self, mount_point: &str) -> Result<()> {
+        let output = self.runner.run("umount", &[mount_point])?;
+
+        if !output.status.success() {
+            anyhow::bail!("failed to umount command: {:?}", output.stderr);
+        };
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::ScriptedRunner;
+
+    #[test]
+    fn replay_mount_and_umount() {
+        let runner = ScriptedRunner::new()
+            .ok("mount -t tmpfs -o size=1024 RAMDisk /mnt/RAMDisk", "")
+            .ok("mount -t tmpfs RAMDisk /mnt/RAMDisk", "")
+            .ok("mount /dev/zram0 /mnt/RAMDisk", "")
+            .fail(
+                "umount /mnt/RAMDisk",
+                "umount: /mnt/RAMDisk: target is busy.",
+            );
+        let mount = Mount::new(&runner);
+
+        mount.tmpfs("RAMDisk", "size=1024", "/mnt/RAMDisk").unwrap();
+        mount.tmpfs("RAMDisk", "", "/mnt/RAMDisk").unwrap();
+        mount.device("/dev/zram0", "/mnt/RAMDisk").unwrap();
+        assert!(mount.umount("/mnt/RAMDisk").is_err());
+        assert!(runner.is_finished());
+    }
+}
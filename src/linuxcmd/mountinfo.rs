@@ -0,0 +1,119 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+
+static MOUNTINFO_PATH: &str = "proc/self/mountinfo";
+
+pub struct MountInfo {
+    pub entries: Vec<MountInfoEntry>,
+}
+
+#[derive(Debug)]
+#[allow(dead_code)]
+pub struct MountInfoEntry {
+    pub mount_point: String,
+    pub fs_type: String,
+    pub source: String,
+}
+
+impl MountInfo {
+    /// Mount table of the system whose `/proc` is under `root`
+    pub fn load_from(root: &Path) -> Result<Self> {
+        let path = root.join(MOUNTINFO_PATH);
+        let c = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.to_string_lossy()))?;
+        MountInfo::parse(&c)
+    }
+
+    pub fn parse(c: &str) -> Result<Self> {
+        let mut entries = vec![];
+        for line in c.lines().filter(|l| !l.trim().is_empty()) {
+            entries.push(MountInfo::parse_line(line)?);
+        }
+        Ok(MountInfo { entries })
+    }
+
+    pub fn find(&self, mount_point: &str) -> Option<&MountInfoEntry> {
+        self.entries
+            .iter()
+            .rev()
+            .find(|e| e.mount_point == mount_point)
+    }
+
+    // 36 35 98:0 /mnt1 /mnt2 rw,noatime master:1 - ext3 /dev/root rw,errors=continue
+    fn parse_line(line: &str) -> Result<MountInfoEntry> {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let separator = fields
+            .iter()
+            .position(|f| *f == "-")
+            .with_context(|| format!("Invalid mountinfo line: {}", line))?;
+        if separator < 5 || fields.len() < separator + 3 {
+            anyhow::bail!("Invalid mountinfo line: {}", line);
+        }
+
+        Ok(MountInfoEntry {
+            mount_point: MountInfo::unescape(fields[4]),
+            fs_type: fields[separator + 1].to_string(),
+            source: MountInfo::unescape(fields[separator + 2]),
+        })
+    }
+
+    // Space, tab, newline and backslash are written as octal escapes like `\040`.
+    fn unescape(field: &str) -> String {
+        let mut out = String::new();
+        let mut chars = field.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                out.push(c);
+                continue;
+            }
+            let octal: String = (0..3).filter_map(|_| chars.next()).collect();
+            match u8::from_str_radix(&octal, 8) {
+                Ok(b) => out.push(b as char),
+                Err(_) => {
+                    out.push(c);
+                    out.push_str(&octal);
+                }
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MOUNTINFO: &str = r#"
+22 1 8:1 / / rw,relatime shared:1 - ext4 /dev/sda1 rw
+36 22 0:32 / /mnt/RAMDisk\040by\040ramup rw,relatime shared:20 - tmpfs RAMDisk rw,size=4194304k
+"#;
+
+    #[test]
+    fn parse() {
+        let info = MountInfo::parse(MOUNTINFO).unwrap();
+        assert_eq!(info.entries.len(), 2);
+
+        let root = info.find("/").unwrap();
+        assert_eq!(root.fs_type, "ext4");
+        assert_eq!(root.source, "/dev/sda1");
+
+        let ram = info.find("/mnt/RAMDisk by ramup").unwrap();
+        assert_eq!(ram.fs_type, "tmpfs");
+        assert_eq!(ram.source, "RAMDisk");
+
+        assert!(info.find("/mnt/RAMDisk").is_none());
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert!(MountInfo::parse("22 1 8:1 / /").is_err());
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn load() {
+        let info = MountInfo::load_from(Path::new("/")).unwrap();
+        assert!(info.find("/").is_some());
+    }
+}
@@ -0,0 +1,162 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+static ZRAM_CONTROL_PATH: &str = "sys/class/zram-control";
+static SYS_BLOCK_PATH: &str = "sys/block";
+
+/// zram device controlled through sysfs under `root`, which is `/` outside of tests.
+pub struct Zram {
+    pub id: u32,
+    root: PathBuf,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ZramStat {
+    pub orig_data_size: u64,
+    pub compr_data_size: u64,
+    pub mem_used_total: u64,
+}
+
+impl Zram {
+    pub fn add(root: &Path) -> Result<Self> {
+        let hot_add = root.join(ZRAM_CONTROL_PATH).join("hot_add");
+        let id = fs::read_to_string(&hot_add)
+            .with_context(|| "Failed to add zram device. Is the zram module loaded?")?;
+        let id = id.trim().parse()?;
+        Ok(Zram {
+            id,
+            root: root.to_path_buf(),
+        })
+    }
+
+    pub fn from_device(root: &Path, device: &str) -> Result<Self> {
+        let id = device
+            .strip_prefix("/dev/zram")
+            .with_context(|| format!("Not a zram device: {}", device))?;
+        Ok(Zram {
+            id: id.parse()?,
+            root: root.to_path_buf(),
+        })
+    }
+
+    pub fn device(&self) -> String {
+        format!("/dev/zram{}", self.id)
+    }
+
+    pub fn set_comp_algorithm(&self, algorithm: &str) -> Result<()> {
+        self.write("comp_algorithm", algorithm)
+    }
+
+    pub fn set_disksize(&self, bytes: u64) -> Result<()> {
+        self.write("disksize", &bytes.to_string())
+    }
+
+    pub fn stat(&self) -> Result<ZramStat> {
+        let path = self.sys_path().join("mm_stat");
+        let c = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.to_string_lossy()))?;
+        ZramStat::parse(&c)
+    }
+
+    pub fn remove(&self) -> Result<()> {
+        self.write("reset", "1")?;
+        let hot_remove = self.root.join(ZRAM_CONTROL_PATH).join("hot_remove");
+        fs::write(&hot_remove, self.id.to_string())
+            .with_context(|| format!("Failed to remove {}", self.device()))
+    }
+
+    fn sys_path(&self) -> PathBuf {
+        self.root
+            .join(SYS_BLOCK_PATH)
+            .join(format!("zram{}", self.id))
+    }
+
+    fn write(&self, attr: &str, value: &str) -> Result<()> {
+        let path = self.sys_path().join(attr);
+        fs::write(&path, value)
+            .with_context(|| format!("Failed to write {}", path.to_string_lossy()))
+    }
+}
+
+impl ZramStat {
+    // orig_data_size compr_data_size mem_used_total mem_limit mem_used_max ...
+    pub fn parse(c: &str) -> Result<Self> {
+        let fields = c
+            .split_whitespace()
+            .map(|f| f.parse::<u64>())
+            .collect::<std::result::Result<Vec<u64>, _>>()?;
+        if fields.len() < 3 {
+            anyhow::bail!("Invalid mm_stat: {}", c);
+        }
+        Ok(ZramStat {
+            orig_data_size: fields[0],
+            compr_data_size: fields[1],
+            mem_used_total: fields[2],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn parse_stat() {
+        let stat = ZramStat::parse(
+            "  4096000   1024000   1200000        0  1200000      12       0       0\n",
+        )
+        .unwrap();
+        assert_eq!(
+            stat,
+            ZramStat {
+                orig_data_size: 4096000,
+                compr_data_size: 1024000,
+                mem_used_total: 1200000,
+            }
+        );
+        assert!(ZramStat::parse("4096000").is_err());
+    }
+
+    #[test]
+    fn from_device() {
+        let root = Path::new("/");
+        assert_eq!(Zram::from_device(root, "/dev/zram3").unwrap().id, 3);
+        assert_eq!(
+            Zram::from_device(root, "/dev/zram3").unwrap().device(),
+            "/dev/zram3"
+        );
+        assert!(Zram::from_device(root, "/dev/sda1").is_err());
+    }
+
+    #[test]
+    fn add_and_remove() {
+        let root = TempDir::new("ramup").unwrap();
+        let control = root.path().join(ZRAM_CONTROL_PATH);
+        fs::create_dir_all(&control).unwrap();
+        fs::create_dir_all(root.path().join(SYS_BLOCK_PATH).join("zram2")).unwrap();
+        fs::write(control.join("hot_add"), "2\n").unwrap();
+
+        let zram = Zram::add(root.path()).unwrap();
+        assert_eq!(zram.device(), "/dev/zram2");
+        zram.set_comp_algorithm("zstd").unwrap();
+        zram.set_disksize(1048576).unwrap();
+        let sys_path = zram.sys_path();
+        assert_eq!(
+            fs::read_to_string(sys_path.join("comp_algorithm")).unwrap(),
+            "zstd"
+        );
+        assert_eq!(
+            fs::read_to_string(sys_path.join("disksize")).unwrap(),
+            "1048576"
+        );
+
+        fs::write(sys_path.join("mm_stat"), "4096 1024 1200 0 1200 0 0 0\n").unwrap();
+        assert_eq!(zram.stat().unwrap().compr_data_size, 1024);
+
+        zram.remove().unwrap();
+        assert_eq!(fs::read_to_string(sys_path.join("reset")).unwrap(), "1");
+        assert_eq!(fs::read_to_string(control.join("hot_remove")).unwrap(), "2");
+    }
+}
@@ -0,0 +1,82 @@
+use crate::appenv;
+use crate::apperror::LockError;
+use anyhow::{Context, Result};
+use fs2::FileExt;
+use std::fs::{self, File};
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const RETRY: Duration = Duration::from_millis(100);
+
+/// Advisory lock which keeps concurrent ramup processes from touching state at the same time.
+/// It is released when dropped.
+pub struct Lock {
+    file: File,
+}
+
+impl Lock {
+    /// Wait up to `timeout` for another ramup to release the lock.
+    pub fn acquire(timeout: Duration) -> Result<Self> {
+        let started = Instant::now();
+        loop {
+            if let Some(lock) = Lock::try_acquire()? {
+                return Ok(lock);
+            }
+            if started.elapsed() >= timeout {
+                return Err(anyhow::anyhow!(LockError::Timeout {
+                    path: appenv::lock(),
+                    waited: timeout.as_secs(),
+                }));
+            }
+            thread::sleep(RETRY);
+        }
+    }
+
+    /// `None` when another ramup holds the lock.
+    pub fn try_acquire() -> Result<Option<Self>> {
+        let lp = appenv::lock();
+        let parent = Path::new(&lp).parent().with_context(|| "No Parent")?;
+        fs::create_dir_all(parent)?;
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lp)
+            .with_context(|| format!("Failed to open lock file: {}", lp))?;
+
+        match file.try_lock_exclusive() {
+            Ok(()) => Ok(Some(Lock { file })),
+            Err(err) if err.kind() == fs2::lock_contended_error().kind() => Ok(None),
+            Err(err) => Err(err).with_context(|| format!("Failed to lock: {}", lp)),
+        }
+    }
+}
+
+impl Drop for Lock {
+    fn drop(&mut self) {
+        let _ = self.file.unlock();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serial_test::serial;
+    use tempdir::TempDir;
+
+    #[test]
+    #[serial]
+    fn acquire() {
+        let dir = TempDir::new("ramup").unwrap();
+        std::env::set_var(appenv::KEY_STATE_PATH, dir.path().join("state.toml"));
+
+        let lock = Lock::acquire(Duration::from_secs(0)).unwrap();
+        assert!(Lock::try_acquire().unwrap().is_none());
+        let err = Lock::acquire(Duration::from_millis(200)).err().unwrap();
+        assert!(err.downcast_ref::<LockError>().is_some());
+
+        drop(lock);
+        assert!(Lock::try_acquire().unwrap().is_some());
+    }
+}
@@ -1,14 +1,60 @@
+use crate::runner::Runner;
 use anyhow::Result;
-use std::process::Command;
+use serde::Deserialize;
 
-pub struct DiskUtil {}
+pub struct DiskUtil<'a> {
+    runner: &'a dyn Runner,
+}
+
+impl<'a> DiskUtil<'a> {
+    pub fn new(runner: &'a dyn Runner) -> Self {
+        DiskUtil { runner }
+    }
+
+    pub fn erasevolume(&self, format: &str, name: &str, mount_point: &str) -> Result<()> {
+        let output = self
+            .runner
+            .run("diskutil", &["erasevolume", format, name, mount_point])?;
+
+        if !output.status.success() {
+            anyhow::bail!("failed to diskutil command: {:?}", output.stderr);
+        };
+
+        Ok(())
+    }
+
+    /// Information on a device or mount point, `None` when diskutil doesn't know it.
+    pub fn info(&self, target: &str) -> Result<Option<DiskUtilInfo>> {
+        let output = self.runner.run("diskutil", &["info", "-plist", target])?;
+
+        if !output.status.success() {
+            return Ok(None);
+        };
+
+        Ok(Some(plist::from_bytes(&output.stdout)?))
+    }
+
+    pub fn unmount(&self, device: &str) -> Result<()> {
+        let output = self.runner.run("diskutil", &["unmount", device])?;
+
+        if !output.status.success() {
+            anyhow::bail!("failed to diskutil command: {:?}", output.stderr);
+        };
+
+        Ok(())
+    }
 
-impl DiskUtil {
-    #[allow(dead_code)]
-    pub fn erasevolume(name: &str, mount_point: &str) -> Result<()> {
-        let output = Command::new("diskutil")
-            .args(&["erasevolume", "HFS+", name, mount_point])
-            .output()?;
+    pub fn mount(&self, device: &str, mount_point: Option<&str>, options: &[&str]) -> Result<()> {
+        let options = options.join(",");
+        let mut args = vec!["mount"];
+        if !options.is_empty() {
+            args.extend(&["-mountOptions", &options]);
+        }
+        if let Some(mount_point) = mount_point {
+            args.extend(&["-mountPoint", mount_point]);
+        }
+        args.push(device);
+        let output = self.runner.run("diskutil", &args)?;
 
         if !output.status.success() {
             anyhow::bail!("failed to diskutil command: {:?}", output.stderr);
@@ -18,19 +64,84 @@ impl DiskUtil {
     }
 }
 
+#[derive(Deserialize, Debug)]
+pub struct DiskUtilInfo {
+    #[serde(rename(deserialize = "DeviceNode"))]
+    pub device_node: String,
+    #[serde(rename(deserialize = "MountPoint"), default)]
+    pub mount_point: String,
+    /// Devices which an APFS volume's container is synthesized from
+    #[serde(rename(deserialize = "APFSPhysicalStores"), default)]
+    pub apfs_physical_stores: Vec<ApfsPhysicalStore>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ApfsPhysicalStore {
+    #[serde(rename(deserialize = "APFSPhysicalStore"))]
+    pub device_identifier: String,
+}
+
 #[cfg(test)]
 mod tests {
     use crate::maccmd::DiskUtil;
-    use crate::maccmd::HdiUtil;
+    use crate::runner::ScriptedRunner;
 
     #[test]
     #[cfg(target_os = "macos")]
     fn erasevolume() {
+        use crate::maccmd::HdiUtil;
+        use crate::runner::SystemRunner;
+
+        let runner = SystemRunner {};
+        let hdiutil = HdiUtil::new(&runner);
         let name = "RAMDiskForTest";
-        let mount_point = HdiUtil::attach(100000).unwrap();
+        let mount_point = hdiutil.attach(100000).unwrap();
 
-        DiskUtil::erasevolume(&name, &mount_point).unwrap();
+        DiskUtil::new(&runner)
+            .erasevolume("HFS+", &name, &mount_point)
+            .unwrap();
         let devname = format!("{}{}", "/Volumes/", name);
-        HdiUtil::detach(&devname).unwrap();
+        hdiutil.detach(&devname).unwrap();
+    }
+
+    #[test]
+    fn replay_erasevolume() {
+        let runner = ScriptedRunner::new()
+            .ok(
+                "diskutil erasevolume HFS+ RAMDiskForTest /dev/disk2",
+                include_str!("fixtures/diskutil_erasevolume.txt"),
+            )
+            .ok("diskutil erasevolume APFSX RAMDiskForTest /dev/disk3", "")
+            .fail("diskutil erasevolume HFS+ RAMDiskForTest /dev/disk9", "");
+        let diskutil = DiskUtil::new(&runner);
+
+        diskutil
+            .erasevolume("HFS+", "RAMDiskForTest", "/dev/disk2")
+            .unwrap();
+        diskutil
+            .erasevolume("APFSX", "RAMDiskForTest", "/dev/disk3")
+            .unwrap();
+        assert!(diskutil
+            .erasevolume("HFS+", "RAMDiskForTest", "/dev/disk9")
+            .is_err());
+        assert!(runner.is_finished());
+    }
+
+    #[test]
+    fn replay_info() {
+        let runner = ScriptedRunner::new()
+            .ok(
+                "diskutil info -plist /Volumes/RAMDiskByRamup",
+                include_str!("fixtures/diskutil_info_apfs.plist"),
+            )
+            .fail("diskutil info -plist /Volumes/NotExist", "");
+        let diskutil = DiskUtil::new(&runner);
+
+        let info = diskutil.info("/Volumes/RAMDiskByRamup").unwrap().unwrap();
+        assert_eq!(info.device_node, "/dev/disk3s1");
+        assert_eq!(info.mount_point, "/Volumes/RAMDiskByRamup");
+        assert_eq!(info.apfs_physical_stores[0].device_identifier, "disk2");
+        assert!(diskutil.info("/Volumes/NotExist").unwrap().is_none());
+        assert!(runner.is_finished());
     }
 }
@@ -1,14 +1,19 @@
+use crate::runner::Runner;
 use anyhow::Result;
 use plist;
 use serde::Deserialize;
-use std::process::Command;
 
-pub struct HdiUtil {}
+pub struct HdiUtil<'a> {
+    runner: &'a dyn Runner,
+}
+
+impl<'a> HdiUtil<'a> {
+    pub fn new(runner: &'a dyn Runner) -> Self {
+        HdiUtil { runner }
+    }
 
-impl HdiUtil {
-    #[allow(dead_code)]
-    pub fn info() -> Result<HdiUtilInfo> {
-        let output = Command::new("hdiutil").args(&["info", "-plist"]).output()?;
+    pub fn info(&self) -> Result<HdiUtilInfo> {
+        let output = self.runner.run("hdiutil", &["info", "-plist"])?;
 
         if !output.status.success() {
             anyhow::bail!("failed to hdiutil command: {:?}", output.stderr);
@@ -19,9 +24,8 @@ impl HdiUtil {
         Ok(plist::from_bytes(&output).unwrap())
     }
 
-    #[allow(dead_code)]
-    pub fn exist(devname: &str) -> Result<bool> {
-        let info = HdiUtil::info()?;
+    pub fn exist(&self, devname: &str) -> Result<bool> {
+        let info = self.info()?;
         for image in &info.images {
             if devname == image.system_entities[0].dev_entry {
                 return Ok(true);
@@ -33,19 +37,14 @@ impl HdiUtil {
         Ok(false)
     }
 
-    #[allow(dead_code)]
-    pub fn exist_volume(name: &str) -> Result<bool> {
-        let volume_name = format!("/Volumes/{}", name);
-        HdiUtil::exist(&volume_name)
+    pub fn exist_volume(&self, mount_point: &str) -> Result<bool> {
+        self.exist(mount_point)
     }
 
-    #[allow(dead_code)]
-    pub fn attach(size: isize) -> Result<String> {
-        let image = format!("ram://{}", size);
+    pub fn attach(&self, sectors: u64) -> Result<String> {
+        let image = format!("ram://{}", sectors);
         let image = image.as_str();
-        let output = Command::new("hdiutil")
-            .args(&["attach", "-nomount", image])
-            .output()?;
+        let output = self.runner.run("hdiutil", &["attach", "-nomount", image])?;
 
         if !output.status.success() {
             anyhow::bail!("failed to hdiutil command: {:?}", output.stderr);
@@ -55,11 +54,10 @@ impl HdiUtil {
         Ok(output.trim().to_string())
     }
 
-    #[allow(dead_code)]
-    pub fn detach(mountpoint: &str) -> Result<()> {
-        let output = Command::new("hdiutil")
-            .args(&["detach", "-force", mountpoint])
-            .output()?;
+    pub fn detach(&self, mountpoint: &str) -> Result<()> {
+        let output = self
+            .runner
+            .run("hdiutil", &["detach", "-force", mountpoint])?;
 
         if !output.status.success() {
             anyhow::bail!("failed to hdiutil command: {:?}", output.stderr);
@@ -67,10 +65,8 @@ impl HdiUtil {
         Ok(())
     }
 
-    #[allow(dead_code)]
-    pub fn detach_volume(name: &str) -> Result<()> {
-        let volume_name = format!("/Volumes/{}", name);
-        HdiUtil::detach(&volume_name)
+    pub fn detach_volume(&self, mount_point: &str) -> Result<()> {
+        self.detach(mount_point)
     }
 }
 
@@ -121,11 +117,18 @@ pub struct HdiUtilInfoImageSystemEntity {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::runner::ScriptedRunner;
+    #[cfg(target_os = "macos")]
+    use crate::runner::SystemRunner;
+
+    const INFO: &str = include_str!("fixtures/hdiutil_info.plist");
+    const INFO_WITH_VOLUME: &str = include_str!("fixtures/hdiutil_info_with_volume.plist");
+    const ATTACH: &str = include_str!("fixtures/hdiutil_attach.txt");
 
     #[test]
     #[cfg(target_os = "macos")]
     fn info() {
-        let hdiutil_info = HdiUtil::info().unwrap();
+        let hdiutil_info = HdiUtil::new(&SystemRunner {}).info().unwrap();
         assert_eq!(hdiutil_info.vendor, "Apple");
     }
 
@@ -134,29 +137,36 @@ mod tests {
     fn exist() {
         use crate::maccmd::DiskUtil;
 
-        let mountpoint = HdiUtil::attach(100).unwrap();
-        assert_eq!(HdiUtil::exist(&mountpoint).unwrap(), true);
-        HdiUtil::detach(&mountpoint).unwrap();
+        let runner = SystemRunner {};
+        let hdiutil = HdiUtil::new(&runner);
+        let mountpoint = hdiutil.attach(100).unwrap();
+        assert_eq!(hdiutil.exist(&mountpoint).unwrap(), true);
+        hdiutil.detach(&mountpoint).unwrap();
 
-        let mountpoint = HdiUtil::attach(10000).unwrap();
+        let mountpoint = hdiutil.attach(10000).unwrap();
         let name = "RAMDiskForExistTest";
-        DiskUtil::erasevolume(&name, &mountpoint).unwrap();
-        assert_eq!(HdiUtil::exist_volume(&name).unwrap(), true);
-        HdiUtil::detach_volume(&name).unwrap();
+        DiskUtil::new(&runner)
+            .erasevolume("HFS+", &name, &mountpoint)
+            .unwrap();
+        let volume = format!("/Volumes/{}", name);
+        assert_eq!(hdiutil.exist_volume(&volume).unwrap(), true);
+        hdiutil.detach_volume(&volume).unwrap();
     }
 
     #[test]
     #[cfg(target_os = "macos")]
     fn not_exist() {
-        assert_eq!(HdiUtil::exist("DevNameForNotExist").unwrap(), false);
+        let hdiutil = HdiUtil::new(&SystemRunner {});
+        assert_eq!(hdiutil.exist("DevNameForNotExist").unwrap(), false);
     }
 
     #[test]
     #[cfg(target_os = "macos")]
     fn attach_and_detach() {
-        let mountpoint = HdiUtil::attach(100).unwrap();
+        let hdiutil = HdiUtil::new(&SystemRunner {});
+        let mountpoint = hdiutil.attach(100).unwrap();
         assert!(mountpoint.len() > 1);
-        HdiUtil::detach(&mountpoint).unwrap();
+        hdiutil.detach(&mountpoint).unwrap();
     }
 
     #[test]
@@ -164,11 +174,48 @@ mod tests {
     fn attach_and_detach_volume() {
         use crate::maccmd::DiskUtil;
 
-        let mountpoint = HdiUtil::attach(10000).unwrap();
+        let runner = SystemRunner {};
+        let hdiutil = HdiUtil::new(&runner);
+        let mountpoint = hdiutil.attach(10000).unwrap();
         let name = "RAMDiskForAttachAndDetachTest";
-        DiskUtil::erasevolume(name, &mountpoint).unwrap();
-        assert_eq!(HdiUtil::exist_volume(&name).unwrap(), true);
-        HdiUtil::detach_volume(name).unwrap();
+        DiskUtil::new(&runner)
+            .erasevolume("HFS+", name, &mountpoint)
+            .unwrap();
+        let volume = format!("/Volumes/{}", name);
+        assert_eq!(hdiutil.exist_volume(&volume).unwrap(), true);
+        hdiutil.detach_volume(&volume).unwrap();
+    }
+
+    #[test]
+    fn replay_attach_and_detach() {
+        let runner = ScriptedRunner::new()
+            .ok("hdiutil attach -nomount ram://8388608", ATTACH)
+            .ok("hdiutil detach -force /dev/disk2", "");
+        let hdiutil = HdiUtil::new(&runner);
+
+        assert_eq!(hdiutil.attach(8388608).unwrap(), "/dev/disk2");
+        hdiutil.detach("/dev/disk2").unwrap();
+        assert!(runner.is_finished());
+    }
+
+    #[test]
+    fn replay_exist() {
+        let runner = ScriptedRunner::new()
+            .ok("hdiutil info -plist", INFO)
+            .ok("hdiutil info -plist", INFO_WITH_VOLUME)
+            .ok("hdiutil info -plist", INFO_WITH_VOLUME);
+        let hdiutil = HdiUtil::new(&runner);
+
+        assert!(!hdiutil.exist_volume("/Volumes/RAMDiskByRamup").unwrap());
+        assert!(hdiutil.exist_volume("/Volumes/RAMDiskByRamup").unwrap());
+        assert!(hdiutil.exist("/dev/disk2").unwrap());
+        assert!(runner.is_finished());
+    }
+
+    #[test]
+    fn replay_failure() {
+        let runner = ScriptedRunner::new().fail("hdiutil attach -nomount ram://100", "error");
+        assert!(HdiUtil::new(&runner).attach(100).is_err());
     }
 
     #[test]
@@ -0,0 +1,40 @@
+use crate::runner::Runner;
+use anyhow::Result;
+
+pub struct MdUtil<'a> {
+    runner: &'a dyn Runner,
+}
+
+impl<'a> MdUtil<'a> {
+    pub fn new(runner: &'a dyn Runner) -> Self {
+        MdUtil { runner }
+    }
+
+    pub fn disable_indexing(&self, mount_point: &str) -> Result<()> {
+        let output = self.runner.run("mdutil", &["-i", "off", mount_point])?;
+
+        if !output.status.success() {
+            anyhow::bail!("failed to mdutil command: {:?}", output.stderr);
+        };
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::ScriptedRunner;
+
+    #[test]
+    fn replay_disable_indexing() {
+        let runner = ScriptedRunner::new().ok(
+            "mdutil -i off /Volumes/RAMDiskByRamup",
+            "/Volumes/RAMDiskByRamup:\n\tIndexing disabled.\n",
+        );
+        MdUtil::new(&runner)
+            .disable_indexing("/Volumes/RAMDiskByRamup")
+            .unwrap();
+        assert!(runner.is_finished());
+    }
+}
@@ -1,5 +1,11 @@
 pub use diskutil::DiskUtil;
 pub use hdiutil::HdiUtil;
+pub use mdutil::MdUtil;
+pub use sysctl::Sysctl;
+pub use tmutil::TmUtil;
 
 mod diskutil;
 mod hdiutil;
+mod mdutil;
+mod sysctl;
+mod tmutil;
@@ -0,0 +1,35 @@
+use crate::runner::Runner;
+use anyhow::Result;
+
+pub struct Sysctl<'a> {
+    runner: &'a dyn Runner,
+}
+
+impl<'a> Sysctl<'a> {
+    pub fn new(runner: &'a dyn Runner) -> Self {
+        Sysctl { runner }
+    }
+
+    pub fn memsize(&self) -> Result<u64> {
+        let output = self.runner.run("sysctl", &["-n", "hw.memsize"])?;
+
+        if !output.status.success() {
+            anyhow::bail!("failed to sysctl command: {:?}", output.stderr);
+        };
+
+        let output = String::from_utf8(output.stdout)?;
+        Ok(output.trim().parse()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::ScriptedRunner;
+
+    #[test]
+    fn replay_memsize() {
+        let runner = ScriptedRunner::new().ok("sysctl -n hw.memsize", "17179869184\n");
+        assert_eq!(Sysctl::new(&runner).memsize().unwrap(), 17179869184);
+    }
+}
@@ -0,0 +1,39 @@
+use crate::runner::Runner;
+use anyhow::Result;
+
+pub struct TmUtil<'a> {
+    runner: &'a dyn Runner,
+}
+
+impl<'a> TmUtil<'a> {
+    pub fn new(runner: &'a dyn Runner) -> Self {
+        TmUtil { runner }
+    }
+
+    pub fn add_exclusion(&self, path: &str) -> Result<()> {
+        let output = self.runner.run("tmutil", &["addexclusion", path])?;
+
+        if !output.status.success() {
+            anyhow::bail!("failed to tmutil command: {:?}", output.stderr);
+        };
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::ScriptedRunner;
+
+    #[test]
+    fn replay_add_exclusion() {
+        let runner = ScriptedRunner::new()
+            .ok("tmutil addexclusion /Volumes/RAMDiskByRamup", "")
+            .fail("tmutil addexclusion /Volumes/NotExist", "Error (100002)");
+        let tmutil = TmUtil::new(&runner);
+
+        tmutil.add_exclusion("/Volumes/RAMDiskByRamup").unwrap();
+        assert!(tmutil.add_exclusion("/Volumes/NotExist").is_err());
+    }
+}
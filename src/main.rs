@@ -3,51 +3,145 @@ mod apperror;
 mod appfs;
 mod application;
 mod apppath;
+mod backend;
 mod cfg;
+mod copy;
+mod daemon;
+mod disk;
+mod doctor;
 mod handler;
+mod interrupt;
+mod journal;
+mod linuxcmd;
+mod lock;
 mod maccmd;
+mod manifest;
 mod ram;
+mod report;
+mod runner;
+mod size;
 mod state;
+mod status;
 mod subcmd;
+mod verify;
 
 use anyhow::{Context, Result};
 use clap::load_yaml;
 use clap::App;
+use std::time::Duration;
 
 //static SUB_COMMAND_INIT: &str = "init";
 static SUB_COMMAND_BACKUP: &str = "backup";
 static SUB_COMMAND_RESTORE: &str = "restore";
-//static SUB_COMMAND_CLEAN: &str = "clean";
+static SUB_COMMAND_CLEAN: &str = "clean";
+static SUB_COMMAND_SYNC: &str = "sync";
+static SUB_COMMAND_DAEMON: &str = "daemon";
+static SUB_COMMAND_UP: &str = "up";
+static SUB_COMMAND_DOCTOR: &str = "doctor";
+static SUB_COMMAND_STATUS: &str = "status";
+static SUB_COMMAND_RECOVER_STATE: &str = "recover-state";
 
 fn main() -> Result<()> {
+    let result = run();
+    if let Err(err) = &result {
+        if err.downcast_ref::<apperror::Interrupted>().is_some() {
+            eprintln!("{}", err);
+            std::process::exit(interrupt::EXIT_CODE);
+        }
+    }
+    result
+}
+
+fn run() -> Result<()> {
     let yaml = load_yaml!("cli.yml");
     let arg_matches = App::from_yaml(yaml).get_matches();
+    report::set_json(arg_matches.value_of("format") == Some("json"));
+
+    if let Some("init") = arg_matches.subcommand_name() {
+        return cfg::Config::initialize();
+    }
+
+    let wait = match arg_matches.value_of("wait") {
+        Some(wait) => wait
+            .parse()
+            .with_context(|| format!("Invalid wait: {}", wait))?,
+        None => 30,
+    };
+    let lock = lock::Lock::acquire(Duration::from_secs(wait))?;
 
     let config = cfg::Config::load()?;
-    let state = state::State::load();
+    let state = match arg_matches.subcommand_matches(SUB_COMMAND_RECOVER_STATE) {
+        Some(matches) if matches.is_present("scan") => {
+            state::State::set_aside()?;
+            state::State::default()
+        }
+        Some(_) => state::State::restore_backup()?,
+        None => match state::State::load() {
+            Err(err)
+                if matches!(
+                    err.downcast_ref(),
+                    Some(apperror::StateError::TooNew { .. })
+                ) =>
+            {
+                return Err(err);
+            }
+            result => result.with_context(|| {
+                "Run `ramup recover-state` to restore the last good state, \
+                 or `ramup recover-state --scan` to rebuild it from the RAMDisk"
+            })?,
+        },
+    };
     let apps = config.applications;
-    let ram = config.ram;
+    let sync = config.sync;
+    let default_disk = config.ram.first().map(|ram| ram.name.clone());
+    let mut disks = vec![];
+    for ram in config.ram {
+        disks.push(disk::Disk::new(ram)?);
+    }
 
-    let mut handler = handler::Handler::new(ram, state);
+    let verify = match arg_matches
+        .subcommand()
+        .1
+        .and_then(|matches| matches.value_of("verify"))
+    {
+        Some(verify) => verify.parse()?,
+        None => verify::Verify::default(),
+    };
+    let mut handler = handler::Handler::new(disks, state)?
+        .with_applications(apps.clone())
+        .with_verify(verify);
+    handler.recover()?;
+
+    if let Some("backup") | Some("restore") = arg_matches.subcommand_name() {
+        interrupt::install()?;
+    }
 
     match arg_matches.subcommand_name() {
-        Some("init") => cfg::Config::initialize()?,
         Some("backup") => {
             let matches = arg_matches
                 .subcommand_matches(SUB_COMMAND_BACKUP)
                 .with_context(|| "Arg not found")?;
-            let mut sources: Vec<String> = vec![];
+            let disk = matches.value_of("disk");
             if matches.is_present("path") {
                 let path = matches.value_of("path").with_context(|| "path not found")?;
-                sources.push(path.to_string());
+                handler.backup(disk, vec![path.to_string()])?;
             } else {
+                let mut groups: Vec<(String, Vec<String>)> = vec![];
                 for app in &apps {
-                    for path in &app.paths {
-                        sources.push(path.clone());
+                    let name = app.disk.clone().or_else(|| default_disk.clone());
+                    let name = name.with_context(|| "No RAMDisk is configured")?;
+                    if disk.is_some_and(|disk| disk != name) {
+                        continue;
                     }
+                    match groups.iter_mut().find(|(n, _)| n == &name) {
+                        Some((_, sources)) => sources.extend(app.paths.clone()),
+                        None => groups.push((name, app.paths.clone())),
+                    }
+                }
+                for (name, sources) in groups {
+                    handler.backup(Some(&name), sources)?;
                 }
             }
-            handler.backup(sources)?
         }
         Some("restore") => {
             let matches = arg_matches
@@ -58,12 +152,74 @@ fn main() -> Result<()> {
                 let path = matches.value_of("path").with_context(|| "path not found")?;
                 sources.push(path.to_string());
             } else {
-                let state = state::State::load();
-                sources = state.backup_paths;
+                sources = handler.restorable_paths(matches.value_of("disk"))?;
+            }
+            handler.restore(matches.value_of("disk"), sources)?
+        }
+        Some("clean") => {
+            let matches = arg_matches
+                .subcommand_matches(SUB_COMMAND_CLEAN)
+                .with_context(|| "Arg not found")?;
+            handler.clean(matches.value_of("disk"))?
+        }
+        Some("sync") => {
+            let matches = arg_matches
+                .subcommand_matches(SUB_COMMAND_SYNC)
+                .with_context(|| "Arg not found")?;
+            handler.sync(matches.value_of("disk"), &sync.path)?
+        }
+        Some("daemon") => {
+            let matches = arg_matches
+                .subcommand_matches(SUB_COMMAND_DAEMON)
+                .with_context(|| "Arg not found")?;
+            let interval = match matches.value_of("interval") {
+                Some(interval) => interval
+                    .parse()
+                    .with_context(|| format!("Invalid interval: {}", interval))?,
+                None => sync.interval,
+            };
+            let daemon = daemon::Daemon::new(&sync.path, interval, &apps)?;
+            drop(lock);
+            daemon.run(&mut handler, matches.value_of("disk"))?
+        }
+        Some("up") => {
+            let matches = arg_matches
+                .subcommand_matches(SUB_COMMAND_UP)
+                .with_context(|| "Arg not found")?;
+            handler.up(matches.value_of("disk"), &sync.path)?
+        }
+        Some("doctor") => {
+            let matches = arg_matches
+                .subcommand_matches(SUB_COMMAND_DOCTOR)
+                .with_context(|| "Arg not found")?;
+            handler.doctor(
+                matches.value_of("disk"),
+                matches.is_present("fix"),
+                &sync.path,
+            )?
+        }
+        Some("status") => {
+            let matches = arg_matches
+                .subcommand_matches(SUB_COMMAND_STATUS)
+                .with_context(|| "Arg not found")?;
+            let status = handler.status(matches.value_of("disk"))?;
+            if report::is_json() {
+                report::emit(&status)?
+            } else {
+                print!("{}", status)
+            }
+        }
+        Some("recover-state") => {
+            let matches = arg_matches
+                .subcommand_matches(SUB_COMMAND_RECOVER_STATE)
+                .with_context(|| "Arg not found")?;
+            if matches.is_present("scan") {
+                let rebuilt = handler.rebuild_state()?;
+                println!("Rebuilt state with {} path(s)", rebuilt);
+            } else {
+                println!("Restored state from {}", appenv::state_backup());
             }
-            handler.restore(sources)?
         }
-        Some("clean") => handler.clean()?,
         _ => (),
     }
 
@@ -0,0 +1,91 @@
+use crate::appfs;
+use crate::state::{Entry, VERSION};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Directory at the root of the RAMDisk volume which holds ramup's own files
+pub const DIR: &str = ".ramup";
+
+/// Self-describing record of the paths relocated onto one RAMDisk,
+/// kept on the volume so that the mapping survives losing `state.toml`.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Manifest {
+    pub version: u32,
+    #[serde(default, rename = "entry")]
+    pub entries: Vec<Entry>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl Manifest {
+    /// Manifest of the RAMDisk mounted at `volume_path`, empty when it has none yet.
+    pub fn load<P: AsRef<Path>>(volume_path: P) -> Result<Self> {
+        let path = volume_path.as_ref().join(DIR).join("manifest.toml");
+        if !path.exists() {
+            return Ok(Manifest {
+                version: VERSION,
+                entries: vec![],
+                path,
+            });
+        }
+        let c = fs::read_to_string(&path)?;
+        let mut manifest: Manifest = toml::from_str(&c)
+            .with_context(|| format!("Failed to parse manifest: {}", path.display()))?;
+        manifest.path = path;
+        Ok(manifest)
+    }
+
+    /// Entry whose original path is `source`
+    pub fn get<P: AsRef<Path>>(&self, source: P) -> Option<&Entry> {
+        let source = source.as_ref().to_string_lossy();
+        self.entries.iter().find(|e| e.source == source)
+    }
+
+    /// Record `entry`, replacing the one for the same original path.
+    pub fn add(&mut self, entry: Entry) -> Result<()> {
+        self.entries.retain(|e| e.source != entry.source);
+        self.entries.push(entry);
+        self.save()
+    }
+
+    pub fn remove<P: AsRef<Path>>(&mut self, source: P) -> Result<()> {
+        let source = source.as_ref().to_string_lossy();
+        if let Some(index) = self.entries.iter().position(|e| e.source == source) {
+            self.entries.remove(index);
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    fn save(&self) -> Result<()> {
+        appfs::write_atomic(&self.path, &toml::to_string(&self)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn add_and_remove() {
+        let dir = TempDir::new("ramup").unwrap();
+
+        let mut manifest = Manifest::load(dir.path()).unwrap();
+        assert!(manifest.entries.is_empty());
+        let mut entry = Entry::new("/this/is/path", dir.path().join("this/is/path"));
+        entry.application = Some("ide".to_string());
+        manifest.add(entry.clone()).unwrap();
+        manifest.add(entry.clone()).unwrap();
+        assert!(dir.path().join(".ramup/manifest.toml").exists());
+
+        let mut manifest = Manifest::load(dir.path()).unwrap();
+        assert_eq!(manifest.entries.len(), 1);
+        assert_eq!(manifest.get("/this/is/path"), Some(&entry));
+
+        manifest.remove("/this/is/path").unwrap();
+        assert!(Manifest::load(dir.path()).unwrap().entries.is_empty());
+    }
+}
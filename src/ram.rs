@@ -1,20 +1,45 @@
+use crate::backend;
+use crate::size::{Headroom, Size};
 use anyhow::Result;
 use serde::Deserialize;
+use std::path::{Path, PathBuf};
 
 #[derive(Deserialize, Debug)]
 #[serde(default)]
 pub struct RAM {
-    pub size: isize,
+    pub size: Size,
+    pub headroom: Headroom,
     pub name: String,
     pub mount_path: String,
+    pub backend: Option<backend::Kind>,
+    pub mode: Option<String>,
+    pub uid: Option<u32>,
+    pub nr_inodes: Option<String>,
+    pub compression: Option<String>,
+    pub filesystem: backend::Filesystem,
+    pub nobrowse: bool,
+    pub noatime: bool,
+    pub spotlight: bool,
+    pub time_machine: bool,
 }
 
 impl Default for RAM {
     fn default() -> Self {
         RAM {
-            size: 8_388_608,
+            size: Size::default(),
+            headroom: Headroom::default(),
             name: "RAMDiskbyRamup".into(),
             mount_path: "/Volumes".into(),
+            backend: None,
+            mode: None,
+            uid: None,
+            nr_inodes: None,
+            compression: None,
+            filesystem: backend::Filesystem::default(),
+            nobrowse: false,
+            noatime: false,
+            spotlight: true,
+            time_machine: true,
         }
     }
 }
@@ -25,4 +50,8 @@ impl RAM {
         let ram: RAM = toml::from_str(c)?;
         Ok(ram)
     }
+
+    pub fn volume_path(&self) -> PathBuf {
+        Path::new(&self.mount_path).join(&self.name)
+    }
 }
@@ -0,0 +1,139 @@
+use crate::apperror;
+use anyhow::Result;
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+
+static JSON: AtomicBool = AtomicBool::new(false);
+
+/// Switch output to JSON lines for `--format json`.
+/// Human readable messages and progress bars are suppressed in this mode.
+pub fn set_json(json: bool) {
+    JSON.store(json, Ordering::SeqCst);
+}
+
+pub fn is_json() -> bool {
+    JSON.load(Ordering::SeqCst)
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    Ok,
+    /// Not applicable to the path: already backed up or not enough space
+    Skipped,
+    Failed,
+}
+
+/// Result of an action on a single path, printed as a JSON line.
+#[derive(Serialize, Debug)]
+pub struct Record {
+    pub action: &'static str,
+    pub source: String,
+    pub target: Option<String>,
+    pub bytes: Option<u64>,
+    pub duration_ms: u64,
+    pub outcome: Outcome,
+    /// Variant name of the `apperror` error
+    pub error: Option<&'static str>,
+    pub message: Option<String>,
+}
+
+impl Record {
+    pub fn new<T>(
+        action: &'static str,
+        source: String,
+        target: Option<String>,
+        bytes: Option<u64>,
+        started: Instant,
+        result: &Result<T>,
+    ) -> Self {
+        let (outcome, error, message) = match result {
+            Ok(_) => (Outcome::Ok, None, None),
+            Err(err) => {
+                let outcome = match err.downcast_ref::<apperror::FileSystemError>() {
+                    Some(apperror::FileSystemError::NotEnoughSpace { .. })
+                    | Some(apperror::FileSystemError::FileIsAlreadySymbolicLink(_)) => {
+                        Outcome::Skipped
+                    }
+                    _ => Outcome::Failed,
+                };
+                (outcome, Some(apperror::kind(err)), Some(err.to_string()))
+            }
+        };
+        Record {
+            action,
+            source,
+            target,
+            bytes,
+            duration_ms: started.elapsed().as_millis() as u64,
+            outcome,
+            error,
+            message,
+        }
+    }
+}
+
+/// Print `value` as a JSON line when `--format json` is given.
+pub fn emit<T: Serialize>(value: &T) -> Result<()> {
+    if is_json() {
+        println!("{}", serde_json::to_string(value)?);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apperror::FileSystemError;
+
+    #[test]
+    fn record() {
+        let started = Instant::now();
+        let result: Result<()> = Err(anyhow::anyhow!(FileSystemError::NotEnoughSpace {
+            path: "/this/is/path".to_string(),
+            required: 2048,
+            available: 1024,
+        }));
+        let record = Record::new(
+            "backup",
+            "/this/is/path".to_string(),
+            Some("/Volumes/RAMDisk/this/is/path".to_string()),
+            Some(2048),
+            started,
+            &result,
+        );
+        assert_eq!(record.outcome, Outcome::Skipped);
+        assert_eq!(record.error, Some("NotEnoughSpace"));
+
+        let json = serde_json::to_value(&record).unwrap();
+        assert_eq!(json["action"], "backup");
+        assert_eq!(json["outcome"], "skipped");
+        assert_eq!(json["bytes"], 2048);
+
+        let result: Result<()> = Err(anyhow::anyhow!(FileSystemError::VerificationFailed {
+            path: "/this/is/path".to_string(),
+            files: vec!["/this/is/path/file.txt".to_string()],
+        }));
+        let record = Record::new("backup", "/a".to_string(), None, None, started, &result);
+        assert_eq!(record.outcome, Outcome::Failed);
+        assert_eq!(record.error, Some("VerificationFailed"));
+
+        let result: Result<()> = Err(anyhow::anyhow!(FileSystemError::UnknownDisk(
+            "/a".to_string()
+        )));
+        let record = Record::new("restore", "/a".to_string(), None, None, started, &result);
+        assert_eq!(record.outcome, Outcome::Failed);
+
+        let result: Result<()> = Err(anyhow::anyhow!(FileSystemError::FileIsAlreadySymbolicLink(
+            "/a".to_string()
+        )));
+        let record = Record::new("backup", "/a".to_string(), None, None, started, &result);
+        assert_eq!(record.outcome, Outcome::Skipped);
+
+        let result: Result<()> = Err(anyhow::anyhow!("unexpected"));
+        let record = Record::new("restore", "/a".to_string(), None, None, started, &result);
+        assert_eq!(record.outcome, Outcome::Failed);
+        assert_eq!(record.error, Some("Other"));
+    }
+}
@@ -0,0 +1,118 @@
+use anyhow::{Context, Result};
+use std::process::{Command, Output};
+use std::rc::Rc;
+
+/// Runs external tools such as `hdiutil` or `mount`.
+pub trait Runner {
+    fn run(&self, program: &str, args: &[&str]) -> Result<Output>;
+}
+
+pub struct SystemRunner {}
+
+impl Runner for SystemRunner {
+    fn run(&self, program: &str, args: &[&str]) -> Result<Output> {
+        Command::new(program)
+            .args(args)
+            .output()
+            .with_context(|| format!("Failed to run {}", program))
+    }
+}
+
+impl<R: Runner + ?Sized> Runner for Rc<R> {
+    fn run(&self, program: &str, args: &[&str]) -> Result<Output> {
+        (**self).run(program, args)
+    }
+}
+
+#[cfg(test)]
+pub use scripted::ScriptedRunner;
+
+#[cfg(test)]
+mod scripted {
+    use super::Runner;
+    use anyhow::Result;
+    use std::cell::RefCell;
+    use std::collections::VecDeque;
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::{ExitStatus, Output};
+
+    /// Replays recorded outputs in order and fails on any unexpected command.
+    #[derive(Default)]
+    pub struct ScriptedRunner {
+        script: RefCell<VecDeque<(String, Output)>>,
+    }
+
+    impl ScriptedRunner {
+        pub fn new() -> Self {
+            ScriptedRunner::default()
+        }
+
+        pub fn ok(self, command: &str, stdout: &str) -> Self {
+            self.push(command, 0, stdout, "")
+        }
+
+        pub fn fail(self, command: &str, stderr: &str) -> Self {
+            self.push(command, 1, "", stderr)
+        }
+
+        pub fn is_finished(&self) -> bool {
+            self.script.borrow().is_empty()
+        }
+
+        fn push(self, command: &str, code: i32, stdout: &str, stderr: &str) -> Self {
+            let output = Output {
+                status: ExitStatus::from_raw(code << 8),
+                stdout: stdout.as_bytes().to_vec(),
+                stderr: stderr.as_bytes().to_vec(),
+            };
+            self.script
+                .borrow_mut()
+                .push_back((command.to_string(), output));
+            self
+        }
+    }
+
+    impl Runner for ScriptedRunner {
+        fn run(&self, program: &str, args: &[&str]) -> Result<Output> {
+            let command = std::iter::once(program)
+                .chain(args.iter().copied())
+                .collect::<Vec<&str>>()
+                .join(" ");
+            let (expected, output) = match self.script.borrow_mut().pop_front() {
+                Some(next) => next,
+                None => anyhow::bail!("unexpected command: {}", command),
+            };
+            if expected != command {
+                anyhow::bail!("expected command `{}`, but got `{}`", expected, command);
+            }
+            Ok(output)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn system_runner() {
+        let output = SystemRunner {}.run("echo", &["ramup"]).unwrap();
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), "ramup\n");
+    }
+
+    #[test]
+    fn scripted_runner() {
+        let runner = ScriptedRunner::new()
+            .ok("hdiutil info -plist", "info")
+            .fail("hdiutil detach -force /dev/disk2", "busy");
+
+        let output = runner.run("hdiutil", &["info", "-plist"]).unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"info");
+
+        assert!(runner.run("hdiutil", &["info"]).is_err());
+        assert!(runner.is_finished());
+        assert!(runner.run("hdiutil", &["info", "-plist"]).is_err());
+    }
+}
@@ -0,0 +1,324 @@
+use crate::apperror::SizeError;
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+use serde::Deserialize as DeriveDeserialize;
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+pub const SECTOR_SIZE: u64 = 512;
+
+/// Smallest RAMDisk created by `size = "auto"`.
+pub const AUTO_MINIMUM: u64 = 64 << 20;
+
+/// RAMDisk size written as sectors (`8388608`), bytes (`"4GiB"`, `"512M"`),
+/// a percentage of physical memory (`"25%"`) or `"auto"`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Size {
+    Sectors(u64),
+    Bytes(u64),
+    Percent(f64),
+    Auto,
+}
+
+/// Extra room added on top of the measured size for `size = "auto"`.
+#[derive(DeriveDeserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(try_from = "String")]
+pub enum Headroom {
+    Percent(f64),
+    Bytes(u64),
+}
+
+impl Size {
+    pub fn bytes(self, physical: Option<u64>) -> Result<u64, SizeError> {
+        let bytes = match self {
+            Size::Sectors(sectors) => sectors
+                .checked_mul(SECTOR_SIZE)
+                .ok_or_else(|| SizeError::Invalid(self.to_string()))?,
+            Size::Bytes(bytes) => bytes,
+            Size::Percent(percent) => match physical {
+                Some(physical) => (physical as f64 * percent / 100.0) as u64,
+                None => return Err(SizeError::PercentWithoutMemory(percent)),
+            },
+            Size::Auto => return Err(SizeError::Invalid(self.to_string())),
+        };
+        match physical {
+            Some(physical) if bytes > physical => Err(SizeError::LargerThanMemory(bytes, physical)),
+            _ => Ok(bytes),
+        }
+    }
+
+    /// Replaces `Auto` with the given required bytes plus headroom.
+    pub fn resolve(self, required: u64, headroom: Headroom) -> Result<Size, SizeError> {
+        match self {
+            Size::Auto => Ok(Size::Bytes(headroom.apply(required)?.max(AUTO_MINIMUM))),
+            size => Ok(size),
+        }
+    }
+
+    pub fn sectors(bytes: u64) -> u64 {
+        bytes.div_ceil(SECTOR_SIZE)
+    }
+
+    fn unit(unit: &str) -> Option<u64> {
+        let unit = unit.to_ascii_lowercase();
+        let multiplier = match unit.as_str() {
+            "" | "b" => 1,
+            "k" | "kib" => 1 << 10,
+            "m" | "mib" => 1 << 20,
+            "g" | "gib" => 1 << 30,
+            "t" | "tib" => 1 << 40,
+            "kb" => 1_000,
+            "mb" => 1_000_000,
+            "gb" => 1_000_000_000,
+            "tb" => 1_000_000_000_000,
+            _ => return None,
+        };
+        Some(multiplier)
+    }
+}
+
+impl Default for Size {
+    fn default() -> Self {
+        Size::Sectors(8_388_608)
+    }
+}
+
+impl FromStr for Size {
+    type Err = SizeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || SizeError::Invalid(s.to_string());
+        let value = s.trim();
+
+        if value.eq_ignore_ascii_case("auto") {
+            return Ok(Size::Auto);
+        }
+
+        if let Some(percent) = value.strip_suffix('%') {
+            let percent: f64 = percent.trim().parse().map_err(|_| invalid())?;
+            if percent <= 0.0 || percent > 100.0 {
+                return Err(invalid());
+            }
+            return Ok(Size::Percent(percent));
+        }
+
+        let split = value
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(value.len());
+        let (number, unit) = value.split_at(split);
+        let number: f64 = number.parse().map_err(|_| invalid())?;
+        let multiplier = Size::unit(unit.trim()).ok_or_else(invalid)?;
+        Ok(Size::Bytes((number * multiplier as f64) as u64))
+    }
+}
+
+impl fmt::Display for Size {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Size::Sectors(sectors) => write!(f, "{}", sectors),
+            Size::Bytes(bytes) => write!(f, "{}B", bytes),
+            Size::Percent(percent) => write!(f, "{}%", percent),
+            Size::Auto => write!(f, "auto"),
+        }
+    }
+}
+
+impl Headroom {
+    pub fn apply(self, bytes: u64) -> Result<u64, SizeError> {
+        let extra = match self {
+            Headroom::Percent(percent) => (bytes as f64 * percent / 100.0) as u64,
+            Headroom::Bytes(extra) => extra,
+        };
+        bytes
+            .checked_add(extra)
+            .ok_or_else(|| SizeError::Invalid(format!("{}B + {}B", bytes, extra)))
+    }
+}
+
+impl Default for Headroom {
+    fn default() -> Self {
+        Headroom::Percent(50.0)
+    }
+}
+
+impl FromStr for Headroom {
+    type Err = SizeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || SizeError::Invalid(s.to_string());
+        let value = s.trim();
+        let value = value.strip_prefix('+').unwrap_or(value).trim();
+
+        if let Some(percent) = value.strip_suffix('%') {
+            let percent: f64 = percent.trim().parse().map_err(|_| invalid())?;
+            if percent < 0.0 {
+                return Err(invalid());
+            }
+            return Ok(Headroom::Percent(percent));
+        }
+
+        match value.parse::<Size>() {
+            Ok(Size::Bytes(bytes)) => Ok(Headroom::Bytes(bytes)),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl TryFrom<String> for Headroom {
+    type Error = SizeError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+struct SizeVisitor;
+
+impl<'de> Visitor<'de> for SizeVisitor {
+    type Value = Size;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a number of sectors or a size like \"4GiB\" or \"25%\"")
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Size, E> {
+        if v < 0 {
+            return Err(E::custom(SizeError::Invalid(v.to_string())));
+        }
+        Ok(Size::Sectors(v as u64))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Size, E> {
+        Ok(Size::Sectors(v))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Size, E> {
+        v.parse().map_err(E::custom)
+    }
+}
+
+impl<'de> Deserialize<'de> for Size {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(SizeVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ram::RAM;
+
+    #[test]
+    fn parse() {
+        assert_eq!("4GiB".parse::<Size>().unwrap(), Size::Bytes(4 << 30));
+        assert_eq!("512M".parse::<Size>().unwrap(), Size::Bytes(512 << 20));
+        assert_eq!(
+            "1.5 GB".parse::<Size>().unwrap(),
+            Size::Bytes(1_500_000_000)
+        );
+        assert_eq!("1024".parse::<Size>().unwrap(), Size::Bytes(1024));
+        assert_eq!("25%".parse::<Size>().unwrap(), Size::Percent(25.0));
+
+        assert_eq!("auto".parse::<Size>().unwrap(), Size::Auto);
+
+        assert!("".parse::<Size>().is_err());
+        assert!("4 Gigs".parse::<Size>().is_err());
+        assert!("0%".parse::<Size>().is_err());
+        assert!("150%".parse::<Size>().is_err());
+    }
+
+    #[test]
+    fn deserialize() {
+        let ram = RAM::new_from_str("size = 8388608").unwrap();
+        assert_eq!(ram.size, Size::Sectors(8388608));
+        let ram = RAM::new_from_str(r#"size = "4GiB""#).unwrap();
+        assert_eq!(ram.size, Size::Bytes(4 << 30));
+        assert!(RAM::new_from_str("size = -1").is_err());
+        assert!(RAM::new_from_str(r#"size = "a lot""#).is_err());
+    }
+
+    #[test]
+    fn bytes() {
+        let physical = Some(16 << 30);
+        assert_eq!(Size::Sectors(8388608).bytes(physical), Ok(4 << 30));
+        assert_eq!(Size::Bytes(4 << 30).bytes(physical), Ok(4 << 30));
+        assert_eq!(Size::Percent(25.0).bytes(physical), Ok(4 << 30));
+        assert_eq!(Size::Bytes(1 << 40).bytes(None), Ok(1 << 40));
+
+        assert_eq!(
+            Size::Bytes(32 << 30).bytes(physical),
+            Err(SizeError::LargerThanMemory(32 << 30, 16 << 30))
+        );
+        assert_eq!(
+            Size::Percent(25.0).bytes(None),
+            Err(SizeError::PercentWithoutMemory(25.0))
+        );
+    }
+
+    #[test]
+    fn resolve() {
+        let headroom = Headroom::Percent(50.0);
+        assert_eq!(
+            Size::Auto.resolve(2 << 30, headroom),
+            Ok(Size::Bytes(3 << 30))
+        );
+        assert_eq!(
+            Size::Auto.resolve(2 << 30, Headroom::Bytes(1 << 30)),
+            Ok(Size::Bytes(3 << 30))
+        );
+        assert_eq!(
+            Size::Auto.resolve(0, headroom),
+            Ok(Size::Bytes(AUTO_MINIMUM))
+        );
+        assert_eq!(
+            Size::Bytes(1 << 30).resolve(2 << 30, headroom),
+            Ok(Size::Bytes(1 << 30))
+        );
+        assert!(Size::Auto.bytes(None).is_err());
+    }
+
+    #[test]
+    fn overflow() {
+        assert_eq!(
+            Size::Sectors(u64::MAX).bytes(None),
+            Err(SizeError::Invalid(u64::MAX.to_string()))
+        );
+        assert!(Size::Auto
+            .resolve(u64::MAX, Headroom::Percent(50.0))
+            .is_err());
+        assert!(Size::Auto
+            .resolve(u64::MAX, Headroom::Bytes(1 << 30))
+            .is_err());
+        assert_eq!(
+            Size::Auto.resolve(u64::MAX, Headroom::Bytes(0)),
+            Ok(Size::Bytes(u64::MAX))
+        );
+    }
+
+    #[test]
+    fn parse_headroom() {
+        assert_eq!("+50%".parse::<Headroom>().unwrap(), Headroom::Percent(50.0));
+        assert_eq!(
+            "150%".parse::<Headroom>().unwrap(),
+            Headroom::Percent(150.0)
+        );
+        assert_eq!(
+            "+1GiB".parse::<Headroom>().unwrap(),
+            Headroom::Bytes(1 << 30)
+        );
+        assert!("+auto".parse::<Headroom>().is_err());
+        assert!("-50%".parse::<Headroom>().is_err());
+
+        let ram = RAM::new_from_str(r#"headroom = "+1GiB""#).unwrap();
+        assert_eq!(ram.headroom, Headroom::Bytes(1 << 30));
+    }
+
+    #[test]
+    fn sectors() {
+        assert_eq!(Size::sectors(4 << 30), 8388608);
+        assert_eq!(Size::sectors(513), 2);
+    }
+}
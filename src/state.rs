@@ -1,63 +1,214 @@
 use crate::appenv;
-use anyhow::{Context, Result};
+use crate::apperror::StateError;
+use crate::appfs;
+use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+/// Version of the state file written by this ramup
+pub const VERSION: u32 = 2;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Policy {
+    /// Data is moved to the RAMDisk and `source` is replaced with a symbolic link
+    #[default]
+    Relocate,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Entry {
+    /// Original path, which is a symbolic link while backed up
+    pub source: String,
+    /// Where the data lives on the RAMDisk
+    pub target: String,
+    pub disk: Option<String>,
+    pub application: Option<String>,
+    /// Size in bytes when it was backed up
+    pub size: Option<u64>,
+    /// Seconds since the Unix epoch when it was backed up
+    pub backed_up_at: Option<u64>,
+    #[serde(default)]
+    pub policy: Policy,
+}
+
+impl Entry {
+    pub fn new<P: AsRef<Path>, Q: AsRef<Path>>(source: P, target: Q) -> Self {
+        Entry {
+            source: String::from(source.as_ref().to_string_lossy()),
+            target: String::from(target.as_ref().to_string_lossy()),
+            disk: None,
+            application: None,
+            size: None,
+            backed_up_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .ok()
+                .map(|d| d.as_secs()),
+            policy: Policy::default(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
 pub struct State {
-    pub backup_paths: Vec<String>,
+    pub version: u32,
+    #[serde(default, rename = "entry")]
+    pub entries: Vec<Entry>,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        State {
+            version: VERSION,
+            entries: vec![],
+        }
+    }
+}
+
+/// State file written before entries had metadata
+#[derive(Deserialize)]
+struct StateV1 {
+    backup_paths: Vec<String>,
 }
 
 impl State {
-    #[allow(dead_code)]
-    pub fn load() -> Self {
+    /// Fails with `StateError` when the state file can't be read or parsed.
+    pub fn load() -> Result<Self> {
+        let state = State::load_from(&appenv::state())?;
+        if state.migrated {
+            state.state.save()?;
+        }
+        Ok(state.state)
+    }
+
+    fn load_from(sp: &str) -> Result<Parsed> {
+        if !Path::new(sp).exists() {
+            return Ok(Parsed {
+                state: State::default(),
+                migrated: false,
+            });
+        }
+        let c = fs::read_to_string(sp).map_err(|err| StateError::Unreadable {
+            path: sp.to_string(),
+            message: err.to_string(),
+        })?;
+        let parsed = State::parse(&c).map_err(|err| match err.downcast::<StateError>() {
+            Ok(err) => anyhow::anyhow!(err),
+            Err(err) => anyhow::anyhow!(StateError::Corrupt {
+                path: sp.to_string(),
+                message: err.to_string(),
+            }),
+        })?;
+        Ok(parsed)
+    }
+
+    /// Replace the state file with the copy of the last good one.
+    pub fn restore_backup() -> Result<Self> {
+        let bp = appenv::state_backup();
+        if !Path::new(&bp).exists() {
+            anyhow::bail!("No backup of the state file: {}", bp);
+        }
+        let parsed = State::load_from(&bp)?;
+        State::set_aside()?;
+        parsed.state.save()?;
+        Ok(parsed.state)
+    }
+
+    /// Move the state file to `state.toml.corrupt` so that it can be inspected later.
+    pub fn set_aside() -> Result<()> {
         let sp = appenv::state();
-        if !Path::new(&sp).exists() {
-            return State::default();
+        if Path::new(&sp).exists() {
+            fs::rename(&sp, format!("{}.corrupt", sp))?;
         }
-        let c = fs::read_to_string(&sp).unwrap();
-        let state: State = toml::from_str(&c).unwrap();
-        state
+        Ok(())
     }
 
-    #[allow(dead_code)]
-    pub fn add<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
-        let path = String::from(path.as_ref().to_string_lossy());
+    /// Parse a state file of any version up to the current one, migrating it to the current one.
+    /// Fails with `StateError::TooNew` for a file written by a newer ramup.
+    fn parse(c: &str) -> Result<Parsed> {
+        let value: toml::Value = toml::from_str(c)?;
+        if value.get("version").is_some() {
+            let state: State = value.try_into()?;
+            if state.version > VERSION {
+                anyhow::bail!(StateError::TooNew {
+                    version: state.version,
+                    supported: VERSION,
+                });
+            }
+            return Ok(Parsed {
+                state,
+                migrated: false,
+            });
+        }
+
+        let v1: StateV1 = value.try_into()?;
+        let entries = v1
+            .backup_paths
+            .iter()
+            .map(|path| {
+                let target = fs::read_link(path).unwrap_or_default();
+                Entry {
+                    backed_up_at: None,
+                    ..Entry::new(path, target)
+                }
+            })
+            .collect();
+        Ok(Parsed {
+            state: State {
+                version: VERSION,
+                entries,
+            },
+            migrated: true,
+        })
+    }
+
+    /// Original paths of every entry
+    pub fn paths(&self) -> Vec<String> {
+        self.entries.iter().map(|e| e.source.clone()).collect()
+    }
 
-        if self.backup_paths.iter().any(|s| s == &path) {
+    pub fn contains<P: AsRef<Path>>(&self, path: P) -> bool {
+        let path = path.as_ref().to_string_lossy();
+        self.entries.iter().any(|e| e.source == path)
+    }
+
+    pub fn add(&mut self, entry: Entry) -> Result<()> {
+        if self.contains(&entry.source) {
             return Ok(());
         };
 
-        self.backup_paths.push(path);
+        self.entries.push(entry);
         self.save()
     }
 
-    #[allow(dead_code)]
     pub fn remove<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
         let path = String::from(path.as_ref().to_string_lossy());
 
-        if let Some(index) = self.backup_paths.iter().position(|s| s == &path) {
-            self.backup_paths.remove(index);
+        if let Some(index) = self.entries.iter().position(|e| e.source == path) {
+            self.entries.remove(index);
             self.save()?;
         }
         Ok(())
     }
 
-    #[allow(dead_code)]
     fn save(&self) -> Result<()> {
         let sp = appenv::state();
-        if !Path::new(&sp).exists() {
-            let parent = Path::new(&sp).parent().with_context(|| "No Parent")?;
-            fs::create_dir_all(parent)?;
-            fs::File::create(&sp)?;
+        if Path::new(&sp).exists() {
+            fs::copy(&sp, appenv::state_backup())?;
         }
         let out = toml::to_string(&self)?;
-        fs::write(&sp, out)?;
-        Ok(())
+        appfs::write_atomic(&sp, &out)
     }
 }
 
+struct Parsed {
+    state: State,
+    /// Whether `state` was converted from an older version
+    migrated: bool,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -82,12 +233,16 @@ backup_paths = [
     fn add() {
         set_up();
 
-        let mut state: State = State::load();
-        assert_eq!(0, state.backup_paths.len());
-        state.add("/this/is/new/path").unwrap();
+        let mut state: State = State::load().unwrap();
+        assert_eq!(0, state.entries.len());
+        let mut entry = Entry::new("/this/is/new/path", "/Volumes/RAMDisk/this/is/new/path");
+        entry.disk = Some("RAMDisk".to_string());
+        entry.size = Some(1024);
+        state.add(entry.clone()).unwrap();
 
-        let state: State = State::load();
-        assert_eq!("/this/is/new/path", state.backup_paths.last().unwrap());
+        let state: State = State::load().unwrap();
+        assert_eq!(state.version, VERSION);
+        assert_eq!(&entry, state.entries.last().unwrap());
     }
 
     #[test]
@@ -95,12 +250,78 @@ backup_paths = [
     fn remove() {
         set_up();
 
-        let mut state: State = toml::from_str(&TOML).unwrap();
-        assert_eq!("/this/is/path/2", state.backup_paths.last().unwrap());
+        let mut state: State = State::parse(TOML).unwrap().state;
+        assert_eq!("/this/is/path/2", state.entries.last().unwrap().source);
         state.remove("/this/is/path/2").unwrap();
 
-        let state: State = State::load();
-        assert_eq!(1, state.backup_paths.len());
-        assert_eq!("/this/is/path/1", state.backup_paths.last().unwrap());
+        let state: State = State::load().unwrap();
+        assert_eq!(1, state.entries.len());
+        assert_eq!("/this/is/path/1", state.entries.last().unwrap().source);
+    }
+
+    #[test]
+    #[serial]
+    fn migrate_v1() {
+        let dir = TempDir::new("ramup").unwrap();
+        let state_path = dir.path().join("state.toml");
+        std::env::set_var(appenv::KEY_STATE_PATH, &state_path);
+        fs::write(&state_path, TOML).unwrap();
+
+        let state = State::load().unwrap();
+        assert_eq!(state.version, VERSION);
+        assert_eq!(state.paths(), vec!["/this/is/path/1", "/this/is/path/2"]);
+        assert_eq!(state.entries[0].policy, Policy::Relocate);
+        assert_eq!(state.entries[0].backed_up_at, None);
+
+        let c = fs::read_to_string(&state_path).unwrap();
+        assert!(c.contains("version = 2"));
+        assert!(c.contains("[[entry]]"));
+    }
+
+    #[test]
+    #[serial]
+    fn corrupt() {
+        let dir = TempDir::new("ramup").unwrap();
+        let state_path = dir.path().join("state.toml");
+        std::env::set_var(appenv::KEY_STATE_PATH, &state_path);
+
+        let mut state = State::load().unwrap();
+        state.add(Entry::new("/this/is/path/1", "")).unwrap();
+        state.add(Entry::new("/this/is/path/2", "")).unwrap();
+        fs::write(&state_path, "version = 2\n[[entry]\nsource = ").unwrap();
+
+        let err = State::load().unwrap_err();
+        match err.downcast_ref::<StateError>() {
+            Some(StateError::Corrupt { path, .. }) => {
+                assert_eq!(path, &state_path.to_string_lossy())
+            }
+            _ => panic!("unexpected error: {:?}", err),
+        }
+
+        let state = State::restore_backup().unwrap();
+        assert_eq!(state.paths(), vec!["/this/is/path/1"]);
+        assert_eq!(State::load().unwrap().paths(), vec!["/this/is/path/1"]);
+        assert!(dir.path().join("state.toml.corrupt").exists());
+    }
+
+    #[test]
+    #[serial]
+    fn too_new() {
+        let dir = TempDir::new("ramup").unwrap();
+        let state_path = dir.path().join("state.toml");
+        std::env::set_var(appenv::KEY_STATE_PATH, &state_path);
+        let c = format!("version = {}\n", VERSION + 1);
+        fs::write(&state_path, &c).unwrap();
+
+        let err = State::load().unwrap_err();
+        match err.downcast_ref::<StateError>() {
+            Some(StateError::TooNew { version, supported }) => {
+                assert_eq!(*version, VERSION + 1);
+                assert_eq!(*supported, VERSION);
+            }
+            _ => panic!("unexpected error: {:?}", err),
+        }
+        // Left alone for the newer ramup
+        assert_eq!(fs::read_to_string(&state_path).unwrap(), c);
     }
 }
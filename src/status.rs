@@ -0,0 +1,86 @@
+use crate::backend::Usage;
+use indicatif::HumanBytes;
+use serde::Serialize;
+use std::fmt;
+
+/// What ramup has relocated, reported by `ramup status`.
+#[derive(Serialize, Debug, Default)]
+pub struct Status {
+    pub disks: Vec<DiskStatus>,
+    pub paths: Vec<PathStatus>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct DiskStatus {
+    pub name: String,
+    pub volume_path: String,
+    pub mounted: bool,
+    /// `None` while the RAMDisk isn't mounted
+    pub usage: Option<Usage>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PathStatus {
+    pub path: String,
+    pub disk: String,
+    /// Where the contents live on the RAMDisk
+    pub ram_path: String,
+    /// `None` when `ram_path` doesn't exist
+    pub size: Option<u64>,
+    pub application: Option<String>,
+    /// Whether `path` is a symbolic link which resolves to `ram_path`
+    pub healthy: bool,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for disk in &self.disks {
+            match &disk.usage {
+                Some(usage) => {
+                    write!(
+                        f,
+                        "{} ({}): {} total, {} used, {} free",
+                        disk.name,
+                        disk.volume_path,
+                        HumanBytes(usage.total),
+                        HumanBytes(usage.used),
+                        HumanBytes(usage.free)
+                    )?;
+                    if let (Some(compressed), Some(uncompressed)) =
+                        (usage.compressed, usage.uncompressed)
+                    {
+                        write!(
+                            f,
+                            ", {} compressed to {}",
+                            HumanBytes(uncompressed),
+                            HumanBytes(compressed)
+                        )?;
+                    }
+                    writeln!(f)?;
+                }
+                None => writeln!(f, "{} ({}): not mounted", disk.name, disk.volume_path)?,
+            }
+        }
+
+        if self.paths.is_empty() {
+            return writeln!(f, "No paths are backed up");
+        }
+        for path in &self.paths {
+            let size = match path.size {
+                Some(size) => HumanBytes(size).to_string(),
+                None => "-".to_string(),
+            };
+            writeln!(
+                f,
+                "{} {} -> {} [{}, {}, {}]",
+                if path.healthy { "ok    " } else { "BROKEN" },
+                path.path,
+                path.ram_path,
+                size,
+                path.application.as_deref().unwrap_or("-"),
+                path.disk
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -1,17 +1,25 @@
 use crate::apperror::FileSystemError;
 use crate::appfs;
 use crate::apppath::AbsPath;
+use crate::journal::{Journal, Operation, Step};
+use crate::verify::Verify;
 use anyhow::{Context, Result};
 
 pub struct Backup {}
 
 impl Backup {
-    pub fn backup(from: &AbsPath, to: &AbsPath) -> Result<String> {
+    pub fn backup(
+        from: &AbsPath,
+        to: &AbsPath,
+        journal: &mut Journal,
+        verify: Verify,
+    ) -> Result<String> {
         Backup::validate(from, to)?;
-        Backup::_backup(from, to)
+        journal.begin(Operation::Backup, from, to)?;
+        Backup::_backup(from, to, journal, verify)
     }
 
-    fn validate(from: &AbsPath, _to: &AbsPath) -> Result<()> {
+    fn validate(from: &AbsPath, to: &AbsPath) -> Result<()> {
         if !&from.as_ref().exists() {
             return Err(anyhow::anyhow!(FileSystemError::NotExist(from.to_string())));
         };
@@ -24,13 +32,21 @@ impl Backup {
             )));
         }
 
-        Ok(())
+        appfs::ensure_space(from, to)
     }
 
-    fn _backup(from: &AbsPath, to: &AbsPath) -> Result<String> {
+    fn _backup(
+        from: &AbsPath,
+        to: &AbsPath,
+        journal: &mut Journal,
+        verify: Verify,
+    ) -> Result<String> {
         std::fs::create_dir_all(&to.parent()?)?;
-        appfs::relocate(&from, &to)?;
+        journal.step(from, Step::Copying)?;
+        appfs::relocate(&from, &to, verify)?;
+        journal.step(from, Step::Moved)?;
         std::os::unix::fs::symlink(&to, &from)?;
+        journal.step(from, Step::Linked)?;
         Ok(from.to_string())
     }
 }
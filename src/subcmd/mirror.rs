@@ -0,0 +1,200 @@
+use crate::copy;
+use anyhow::{Context, Result};
+use filetime::FileTime;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// Number of files changed by `Mirror::mirror`
+#[derive(Debug, Default, PartialEq)]
+pub struct MirrorSummary {
+    pub copied: usize,
+    pub updated: usize,
+    pub removed: usize,
+}
+
+impl fmt::Display for MirrorSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} copied, {} updated, {} removed",
+            self.copied, self.updated, self.removed
+        )
+    }
+}
+
+pub struct Mirror {}
+
+impl Mirror {
+    /// Make `to` an exact copy of `from`.
+    /// Files whose size and modification time are unchanged are skipped and
+    /// entries which no longer exist in `from` are removed from `to`.
+    pub fn mirror<P: AsRef<Path>, Q: AsRef<Path>>(from: P, to: Q) -> Result<MirrorSummary> {
+        let mut summary = MirrorSummary::default();
+        if let Some(parent) = to.as_ref().parent() {
+            fs::create_dir_all(parent)?;
+        }
+        Mirror::_mirror(from.as_ref(), to.as_ref(), &mut summary)?;
+        Ok(summary)
+    }
+
+    fn _mirror(from: &Path, to: &Path, summary: &mut MirrorSummary) -> Result<()> {
+        let from_meta = fs::symlink_metadata(from)
+            .with_context(|| format!("Failed to get metadata: {}", from.display()))?;
+        let to_meta = fs::symlink_metadata(to).ok();
+
+        if from_meta.is_dir() {
+            if let Some(to_meta) = &to_meta {
+                if !to_meta.is_dir() {
+                    summary.removed += Mirror::remove(to)?;
+                }
+            }
+            fs::create_dir_all(to)?;
+
+            for entry in fs::read_dir(from)? {
+                let entry = entry?;
+                Mirror::_mirror(&entry.path(), &to.join(entry.file_name()), summary)?;
+            }
+            for entry in fs::read_dir(to)? {
+                let entry = entry?;
+                if fs::symlink_metadata(from.join(entry.file_name())).is_err() {
+                    summary.removed += Mirror::remove(&entry.path())?;
+                }
+            }
+            return Ok(());
+        }
+
+        if copy::is_special(&from_meta.file_type()) {
+            // FIFOs and sockets only mean something while their program runs
+            return Ok(());
+        }
+
+        let existed = match &to_meta {
+            Some(to_meta) if Mirror::is_same(from, &from_meta, to, to_meta) => return Ok(()),
+            Some(_) => {
+                Mirror::remove(to)?;
+                true
+            }
+            None => false,
+        };
+
+        if from_meta.file_type().is_symlink() {
+            std::os::unix::fs::symlink(fs::read_link(from)?, to)?;
+        } else {
+            fs::copy(from, to).with_context(|| format!("Failed to copy: {}", from.display()))?;
+            // Without opening `to`, which may be read-only like the copied file
+            filetime::set_file_mtime(to, FileTime::from_last_modification_time(&from_meta))?;
+        }
+
+        if existed {
+            summary.updated += 1;
+        } else {
+            summary.copied += 1;
+        }
+        Ok(())
+    }
+
+    fn is_same(from: &Path, from_meta: &fs::Metadata, to: &Path, to_meta: &fs::Metadata) -> bool {
+        if from_meta.file_type().is_symlink() {
+            return to_meta.file_type().is_symlink()
+                && fs::read_link(from).ok() == fs::read_link(to).ok();
+        }
+        to_meta.is_file()
+            && from_meta.len() == to_meta.len()
+            && from_meta.modified().ok() == to_meta.modified().ok()
+    }
+
+    /// Remove `path` and return the number of files removed
+    fn remove(path: &Path) -> Result<usize> {
+        let meta = fs::symlink_metadata(path)?;
+        if !meta.is_dir() {
+            fs::remove_file(path)?;
+            return Ok(1);
+        }
+
+        let mut removed = 0;
+        for entry in fs::read_dir(path)? {
+            removed += Mirror::remove(&entry?.path())?;
+        }
+        fs::remove_dir(path)?;
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn mirror() {
+        let tmp_dir = TempDir::new("ramup-mirror").unwrap();
+        let from = tmp_dir.path().join("from");
+        let to = tmp_dir.path().join("to");
+        fs::create_dir_all(from.join("dir")).unwrap();
+        fs::write(from.join("a.txt"), "a").unwrap();
+        fs::write(from.join("dir/b.txt"), "b").unwrap();
+        std::os::unix::fs::symlink("a.txt", from.join("link")).unwrap();
+
+        let summary = Mirror::mirror(&from, &to).unwrap();
+        assert_eq!(
+            summary,
+            MirrorSummary {
+                copied: 3,
+                updated: 0,
+                removed: 0
+            }
+        );
+        assert_eq!(fs::read_to_string(to.join("dir/b.txt")).unwrap(), "b");
+        assert_eq!(fs::read_link(to.join("link")).unwrap(), Path::new("a.txt"));
+
+        let summary = Mirror::mirror(&from, &to).unwrap();
+        assert_eq!(summary, MirrorSummary::default());
+
+        fs::write(from.join("a.txt"), "updated").unwrap();
+        fs::remove_dir_all(from.join("dir")).unwrap();
+        let summary = Mirror::mirror(&from, &to).unwrap();
+        assert_eq!(
+            summary,
+            MirrorSummary {
+                copied: 0,
+                updated: 1,
+                removed: 1
+            }
+        );
+        assert_eq!(fs::read_to_string(to.join("a.txt")).unwrap(), "updated");
+        assert!(!to.join("dir").exists());
+    }
+
+    #[test]
+    fn mirror_read_only() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let tmp_dir = TempDir::new("ramup-mirror").unwrap();
+        let from = tmp_dir.path().join("from");
+        let to = tmp_dir.path().join("to");
+        fs::create_dir_all(&from).unwrap();
+        let object = from.join("object");
+        fs::write(&object, "object").unwrap();
+        fs::set_permissions(&object, fs::Permissions::from_mode(0o444)).unwrap();
+
+        Mirror::mirror(&from, &to).unwrap();
+        let meta = fs::metadata(to.join("object")).unwrap();
+        assert_eq!(meta.permissions().mode() & 0o777, 0o444);
+        assert_eq!(
+            meta.modified().unwrap(),
+            fs::metadata(&object).unwrap().modified().unwrap()
+        );
+
+        fs::set_permissions(&object, fs::Permissions::from_mode(0o644)).unwrap();
+        fs::write(&object, "updated").unwrap();
+        fs::set_permissions(&object, fs::Permissions::from_mode(0o444)).unwrap();
+        let summary = Mirror::mirror(&from, &to).unwrap();
+        assert_eq!(summary.updated, 1);
+        assert_eq!(fs::read_to_string(to.join("object")).unwrap(), "updated");
+        assert_eq!(
+            Mirror::mirror(&from, &to).unwrap(),
+            MirrorSummary::default()
+        );
+    }
+}
@@ -1,5 +1,7 @@
 pub use backup::Backup;
+pub use mirror::Mirror;
 pub use restore::Restore;
 
 mod backup;
+mod mirror;
 mod restore;
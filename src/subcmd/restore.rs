@@ -1,14 +1,22 @@
 use crate::apperror::FileSystemError;
 use crate::appfs;
 use crate::apppath::AbsPath;
+use crate::journal::{Journal, Operation, Step};
+use crate::verify::Verify;
 use anyhow::{Context, Result};
 
 pub struct Restore {}
 
 impl Restore {
-    pub fn restore(from: &AbsPath, to: &AbsPath) -> Result<String> {
+    pub fn restore(
+        from: &AbsPath,
+        to: &AbsPath,
+        journal: &mut Journal,
+        verify: Verify,
+    ) -> Result<String> {
         Restore::validate(from, to)?;
-        Restore::_restore(from, to)
+        journal.begin(Operation::Restore, to, from)?;
+        Restore::_restore(from, to, journal, verify)
     }
 
     fn validate(from: &AbsPath, to: &AbsPath) -> Result<()> {
@@ -29,12 +37,19 @@ impl Restore {
             return Err(anyhow::anyhow!(FileSystemError::NotExist(from.to_string())));
         }
 
-        Ok(())
+        appfs::ensure_space(from, &to.parent()?)
     }
 
-    fn _restore(from: &AbsPath, to: &AbsPath) -> Result<String> {
+    fn _restore(
+        from: &AbsPath,
+        to: &AbsPath,
+        journal: &mut Journal,
+        verify: Verify,
+    ) -> Result<String> {
+        journal.step(to, Step::Copying)?;
         std::fs::remove_file(to).with_context(|| "Cannot Delete file")?;
-        appfs::relocate(&from, &to).with_context(|| "cannot relocate file")?;
+        appfs::relocate(&from, &to, verify).with_context(|| "cannot relocate file")?;
+        journal.step(to, Step::Moved)?;
         Ok(to.to_string())
     }
 }
@@ -0,0 +1,122 @@
+use crate::copy;
+use anyhow::{Context, Result};
+use filetime::FileTime;
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+/// How a copy is checked against the original before the original is removed.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Verify {
+    #[default]
+    Off,
+    /// Same size and modification time of every file
+    Quick,
+    /// Same SHA-256 of the contents of every file
+    Hash,
+}
+
+impl FromStr for Verify {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(Verify::Off),
+            "quick" => Ok(Verify::Quick),
+            "hash" => Ok(Verify::Hash),
+            _ => anyhow::bail!("Invalid verify mode: {}", s),
+        }
+    }
+}
+
+impl Verify {
+    /// Paths under `from` whose copy under `to` is missing or differs.
+    pub fn compare(self, from: &Path, to: &Path) -> Result<Vec<String>> {
+        let mut differ = vec![];
+        if self != Verify::Off {
+            self.compare_tree(from, to, &mut differ)?;
+        }
+        Ok(differ)
+    }
+
+    fn compare_tree(self, from: &Path, to: &Path, differ: &mut Vec<String>) -> Result<()> {
+        let from_meta = fs::symlink_metadata(from)
+            .with_context(|| format!("Failed to get metadata: {}", from.display()))?;
+        let to_meta = match fs::symlink_metadata(to) {
+            Ok(meta) if meta.file_type() == from_meta.file_type() => meta,
+            _ => {
+                differ.push(from.to_string_lossy().to_string());
+                return Ok(());
+            }
+        };
+
+        let same = if from_meta.file_type().is_symlink() {
+            fs::read_link(from)? == fs::read_link(to)?
+        } else if copy::is_special(&from_meta.file_type()) {
+            // Nothing to read without blocking; the node itself is what was copied
+            true
+        } else if from_meta.is_dir() {
+            for entry in fs::read_dir(from)? {
+                let entry = entry?;
+                self.compare_tree(&entry.path(), &to.join(entry.file_name()), differ)?;
+            }
+            true
+        } else if from_meta.len() != to_meta.len() {
+            false
+        } else if self == Verify::Quick {
+            FileTime::from_last_modification_time(&from_meta)
+                == FileTime::from_last_modification_time(&to_meta)
+        } else {
+            Verify::hash(from)? == Verify::hash(to)?
+        };
+        if !same {
+            differ.push(from.to_string_lossy().to_string());
+        }
+        Ok(())
+    }
+
+    fn hash(path: &Path) -> Result<Vec<u8>> {
+        let mut file = File::open(path)?;
+        let mut hasher = Sha256::new();
+        io::copy(&mut file, &mut hasher)?;
+        Ok(hasher.finalize().to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn compare() {
+        let dir = TempDir::new("ramup").unwrap();
+        let (from, to) = (dir.path().join("from"), dir.path().join("to"));
+        for root in &[&from, &to] {
+            fs::create_dir_all(root.join("dir")).unwrap();
+            fs::write(root.join("same.txt"), "ramup").unwrap();
+            fs::write(root.join("dir/flipped.txt"), "ramup").unwrap();
+            std::os::unix::fs::symlink("same.txt", root.join("link")).unwrap();
+        }
+        fs::write(from.join("missing.txt"), "ramup").unwrap();
+        fs::write(to.join("dir/flipped.txt"), "RAMUP").unwrap();
+        let then = FileTime::from_unix_time(1_000_000_000, 0);
+        for root in &[&from, &to] {
+            filetime::set_file_mtime(root.join("same.txt"), then).unwrap();
+            filetime::set_file_mtime(root.join("dir/flipped.txt"), then).unwrap();
+        }
+
+        assert!(Verify::Off.compare(&from, &to).unwrap().is_empty());
+        let missing = from.join("missing.txt").to_string_lossy().to_string();
+        assert_eq!(
+            Verify::Quick.compare(&from, &to).unwrap(),
+            vec![missing.clone()]
+        );
+        let mut differ = Verify::Hash.compare(&from, &to).unwrap();
+        differ.sort();
+        let flipped = from.join("dir/flipped.txt").to_string_lossy().to_string();
+        assert_eq!(differ, vec![flipped, missing]);
+    }
+}